@@ -4,18 +4,36 @@
 
 use std::ffi::{c_int, c_void};
 use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
     ffi::{CString, NulError},
+    fmt,
     marker::PhantomData,
+    mem,
+    rc::Rc,
+    time::Duration,
 };
 
 use snafu::prelude::*;
 
 use xplane_sys::{
     XPLMCommandBegin, XPLMCommandEnd, XPLMCommandOnce, XPLMCommandPhase, XPLMCommandRef,
-    XPLMCreateCommand, XPLMFindCommand, XPLMRegisterCommandHandler, XPLMUnregisterCommandHandler,
+    XPLMCreateCommand, XPLMFindCommand, XPLMGetElapsedTime, XPLMRegisterCommandHandler,
+    XPLMUnregisterCommandHandler,
 };
 
-use crate::{make_x, NoSendSync, XPAPI};
+use crate::{
+    data::DataRead,
+    flight_loop::{FlightLoop, FlightLoopPhase, LoopResult, LoopState},
+    make_x, NoSendSync, XPAPI,
+};
+
+thread_local! {
+    /// Commands held by [`OwnedCommandHold`]s and [`Command::hold_for`]
+    static HOLDS: RefCell<HoldRegistry> = const { RefCell::new(HoldRegistry::new()) };
+    /// The interceptors of each intercepted command
+    static INTERCEPTS: RefCell<Vec<Rc<InterceptChain>>> = const { RefCell::new(Vec::new()) };
+}
 
 /// Struct to access X-Plane's command API.
 pub struct CommandApi {
@@ -41,6 +59,30 @@ impl CommandApi {
     pub fn try_find(&mut self, name: &str) -> Result<Command, CommandFindError> {
         Command::try_find(name)
     }
+
+    /// Intercepts a command created by X-Plane or another plugin
+    ///
+    /// The interceptor runs before X-Plane handles the command, and can prevent X-Plane from
+    /// handling it by returning [`CommandHandlerResult::DisallowXPlaneProcessing`].
+    ///
+    /// Several interceptors can be registered for one command. They run in the order they
+    /// were registered. If one returns `DisallowXPlaneProcessing`, the interceptors after it
+    /// are not called for that phase.
+    ///
+    /// If an interceptor triggers or holds the command itself, that press bypasses the
+    /// interceptors and goes straight to X-Plane.
+    ///
+    /// The command is intercepted until the returned [`Interception`] is dropped.
+    /// # Errors
+    /// Errors if the command could not be found.
+    pub fn intercept(
+        &mut self,
+        name: &str,
+        interceptor: impl CommandInterceptor,
+    ) -> Result<Interception, CommandFindError> {
+        let command = Command::try_find(name)?;
+        Ok(Interception::new(command.id, name, interceptor))
+    }
 }
 
 /// A command created by X-Plane or another plugin, that can be triggered
@@ -107,6 +149,23 @@ impl Command {
         }
     }
 
+    /// Starts holding down this command, and returns an owned hold
+    ///
+    /// Unlike [`hold_down`](Self::hold_down), the returned hold does not borrow this command,
+    /// so it can be stored while the command is used elsewhere. The command will be released
+    /// when the hold is dropped.
+    pub fn hold(&mut self) -> OwnedCommandHold {
+        unsafe {
+            XPLMCommandBegin(self.id);
+        }
+        OwnedCommandHold::new(self.id)
+    }
+
+    /// Holds down this command for `duration`, and then releases it from a flight loop.
+    pub fn hold_for(&mut self, duration: Duration) {
+        self.hold().release_after(duration);
+    }
+
     /// Releases this command
     fn release(&mut self) {
         unsafe {
@@ -123,6 +182,38 @@ impl Command {
     ) -> RegisteredCommandHandler {
         RegisteredCommandHandler::new(self, handler, before)
     }
+
+    /// Registers a closure that is called when this command is pressed.
+    ///
+    /// The closure runs after X-Plane has handled the command.
+    pub fn on_press(
+        &mut self,
+        callback: impl FnMut(&mut XPAPI) + 'static,
+    ) -> RegisteredCommandHandler {
+        self.handle(PhaseHandler::new(XPLMCommandPhase::Begin, callback), false)
+    }
+
+    /// Registers a closure that is called when this command is released.
+    ///
+    /// The closure runs after X-Plane has handled the command.
+    pub fn on_release(
+        &mut self,
+        callback: impl FnMut(&mut XPAPI) + 'static,
+    ) -> RegisteredCommandHandler {
+        self.handle(PhaseHandler::new(XPLMCommandPhase::End, callback), false)
+    }
+
+    /// Registers a closure that is called when this command is pressed, and then repeatedly
+    /// every `interval` while it is held down.
+    ///
+    /// This is the same as handling the command with [`HoldHandler::new`].
+    pub fn on_hold(
+        &mut self,
+        interval: Duration,
+        callback: impl FnMut(&mut XPAPI) + 'static,
+    ) -> RegisteredCommandHandler {
+        self.handle(HoldHandler::new(interval, callback), false)
+    }
 }
 
 /// An RAII lock that keeps a command held down.
@@ -135,12 +226,459 @@ pub struct CommandHold<'a> {
     _phantom: NoSendSync,
 }
 
+impl<'a> CommandHold<'a> {
+    /// Converts this hold into an [`OwnedCommandHold`], ending the borrow of the command.
+    ///
+    /// The command stays held down.
+    #[must_use]
+    pub fn detach(self) -> OwnedCommandHold {
+        let id = self.command.id;
+        mem::forget(self);
+        OwnedCommandHold::new(id)
+    }
+}
+
 impl<'a> Drop for CommandHold<'a> {
     fn drop(&mut self) {
         self.command.release();
     }
 }
 
+/// A command held down, that does not borrow its [`Command`]
+///
+/// The command will be released when this object is dropped. Every command still held is
+/// also released when the plugin is disabled or stopped, after
+/// [`Plugin::disable`](crate::plugin::Plugin::disable) returns.
+pub struct OwnedCommandHold {
+    state: Rc<HoldState>,
+}
+
+struct HoldState {
+    command: XPLMCommandRef,
+    released: Cell<bool>,
+    /// The elapsed time when the command should be released, if it is timed
+    release_at: Cell<Option<Duration>>,
+}
+
+impl OwnedCommandHold {
+    /// Tracks a command that has already been held down
+    fn new(command: XPLMCommandRef) -> Self {
+        let state = Rc::new(HoldState {
+            command,
+            released: Cell::new(false),
+            release_at: Cell::new(None),
+        });
+        HOLDS.with_borrow_mut(|holds| holds.holds.push(Rc::clone(&state)));
+        OwnedCommandHold { state }
+    }
+
+    /// Returns true if the command has not been released yet.
+    ///
+    /// This is false after the plugin has been disabled.
+    #[must_use]
+    pub fn is_held(&self) -> bool {
+        !self.state.released.get()
+    }
+
+    /// Releases the command now. This is the same as dropping the hold.
+    pub fn release(self) {
+        drop(self);
+    }
+
+    /// Keeps the command held down for `duration`, and then releases it from a flight loop.
+    pub fn release_after(self, duration: Duration) {
+        if !self.is_held() {
+            return;
+        }
+        self.state.release_at.set(Some(elapsed_time() + duration));
+        HOLDS.with_borrow_mut(HoldRegistry::start_timer);
+    }
+}
+
+impl Drop for OwnedCommandHold {
+    fn drop(&mut self) {
+        // The registry keeps a timed hold until the timer releases it.
+        if self.state.release_at.get().is_some() {
+            return;
+        }
+        self.state.release();
+        HOLDS.with_borrow_mut(|holds| holds.holds.retain(|state| !state.released.get()));
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for OwnedCommandHold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedCommandHold")
+            .field("command", &"[command handle]")
+            .field("held", &self.is_held())
+            .finish()
+    }
+}
+
+impl HoldState {
+    /// Releases the command, unless that has already been done
+    fn release(&self) {
+        if !self.released.replace(true) {
+            unsafe {
+                XPLMCommandEnd(self.command);
+            }
+        }
+    }
+}
+
+struct HoldRegistry {
+    /// Every command that is held down
+    holds: Vec<Rc<HoldState>>,
+    /// The flight loop that releases timed holds
+    timer: Option<FlightLoop<()>>,
+}
+
+impl HoldRegistry {
+    const fn new() -> Self {
+        HoldRegistry {
+            holds: Vec::new(),
+            timer: None,
+        }
+    }
+
+    /// Schedules the timer for the next flight loop cycle, creating it if needed
+    fn start_timer(&mut self) {
+        let timer = self.timer.get_or_insert_with(|| {
+            let callback = |_x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+                if release_expired() {
+                    LoopResult::NextLoop
+                } else {
+                    LoopResult::Deactivate
+                }
+            };
+            FlightLoop::new(FlightLoopPhase::BeforeFlightModel, callback, ())
+        });
+        timer.schedule_immediate();
+    }
+}
+
+/// Releases timed holds that have expired. Returns true if any timed holds remain.
+fn release_expired() -> bool {
+    let now = elapsed_time();
+    let (expired, remaining): (Vec<_>, Vec<_>) = HOLDS.with_borrow_mut(|holds| {
+        mem::take(&mut holds.holds)
+            .into_iter()
+            .partition(|state| state.release_at.get().is_some_and(|end| now >= end))
+    });
+    let timed = remaining
+        .iter()
+        .any(|state| state.release_at.get().is_some());
+    HOLDS.with_borrow_mut(|holds| holds.holds.extend(remaining));
+    // Commands are released outside of the borrow, as their handlers may hold other commands.
+    for state in expired {
+        state.release();
+    }
+    timed
+}
+
+/// Releases every command that is still held. Called when the plugin is disabled or stopped.
+pub(crate) fn release_all_holds() {
+    let (holds, timer) =
+        HOLDS.with_borrow_mut(|holds| (mem::take(&mut holds.holds), holds.timer.take()));
+    drop(timer);
+    for state in holds {
+        state.release();
+    }
+}
+
+/// A series of command presses, holds and waits, run one after another over time
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use xplane::{command::CommandSequence, data::DataRead, flight_loop::FlightLoopPhase, XPAPI};
+///
+/// fn start_engine(x: &mut XPAPI) -> xplane::command::RunningSequence {
+///     let starter = x.command.try_find("sim/starters/engage_starter_1").unwrap();
+///     let fuel_pump = x.command.try_find("sim/fuel/fuel_pump_1_on").unwrap();
+///     let n2 = x.data.find::<[f32; 1], _>("sim/flightmodel/engine/ENGN_N2_").unwrap();
+///     CommandSequence::new()
+///         .trigger(&fuel_pump)
+///         .wait(Duration::from_millis(500))
+///         .hold_for(&starter, Duration::from_secs(3))
+///         .wait_until(n2, |n2| n2[0] > 50.0)
+///         .start(FlightLoopPhase::BeforeFlightModel)
+/// }
+/// ```
+#[derive(Default)]
+pub struct CommandSequence {
+    steps: VecDeque<SequenceStep>,
+}
+
+/// One step of a [`CommandSequence`]
+enum SequenceStep {
+    /// Triggers a command once
+    Trigger(XPLMCommandRef),
+    /// Holds a command down for a time
+    Hold(XPLMCommandRef, Duration),
+    /// Does nothing for a time
+    Wait(Duration),
+    /// Does nothing until the condition returns true
+    WaitUntil(Box<dyn FnMut() -> bool>),
+}
+
+impl CommandSequence {
+    /// Creates an empty sequence.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a step that triggers `command` once.
+    #[must_use]
+    pub fn trigger(mut self, command: &Command) -> Self {
+        self.steps.push_back(SequenceStep::Trigger(command.id));
+        self
+    }
+
+    /// Adds a step that holds `command` down for `duration`, and then releases it.
+    #[must_use]
+    pub fn hold_for(mut self, command: &Command, duration: Duration) -> Self {
+        self.steps
+            .push_back(SequenceStep::Hold(command.id, duration));
+        self
+    }
+
+    /// Adds a step that waits for `duration`.
+    #[must_use]
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.steps.push_back(SequenceStep::Wait(duration));
+        self
+    }
+
+    /// Adds a step that waits until `predicate` returns true for the value of `dataref`.
+    ///
+    /// The dataref is read once per flight loop cycle while this step is running.
+    #[must_use]
+    pub fn wait_until<T, D>(
+        mut self,
+        dataref: D,
+        mut predicate: impl FnMut(T) -> bool + 'static,
+    ) -> Self
+    where
+        D: DataRead<T> + 'static,
+    {
+        let condition = move || predicate(dataref.get());
+        self.steps
+            .push_back(SequenceStep::WaitUntil(Box::new(condition)));
+        self
+    }
+
+    /// Returns the number of steps in this sequence.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns true if this sequence has no steps.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Starts running this sequence from a flight loop in `phase`.
+    ///
+    /// The first step runs in the next flight loop cycle. The sequence stops if the returned
+    /// [`RunningSequence`] is dropped.
+    #[must_use = "the sequence is cancelled when the RunningSequence is dropped"]
+    pub fn start(self, phase: FlightLoopPhase) -> RunningSequence {
+        let state = Rc::new(RefCell::new(SequenceState {
+            steps: self.steps,
+            active: None,
+            cancelled: false,
+        }));
+        let loop_state = Rc::clone(&state);
+        let callback = move |_x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+            let now = elapsed_time();
+            loop {
+                // Commands run outside of the borrow, as their handlers may use the sequence.
+                let action = loop_state.borrow_mut().advance(now);
+                match action {
+                    SequenceAction::Wait => return LoopResult::NextLoop,
+                    SequenceAction::Stop => return LoopResult::Deactivate,
+                    SequenceAction::Trigger(command) => unsafe {
+                        XPLMCommandOnce(command);
+                    },
+                    SequenceAction::Begin(command, end) => {
+                        unsafe {
+                            XPLMCommandBegin(command);
+                        }
+                        let hold = OwnedCommandHold::new(command);
+                        let cancelled = loop_state.borrow().cancelled;
+                        if cancelled {
+                            hold.release();
+                        } else {
+                            loop_state.borrow_mut().active =
+                                Some(ActiveStep::Holding { hold, end });
+                        }
+                    }
+                    SequenceAction::Release(hold) => hold.release(),
+                }
+            }
+        };
+        let mut flight_loop = FlightLoop::new(phase, callback, ());
+        flight_loop.schedule_immediate();
+        RunningSequence { state, flight_loop }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for CommandSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandSequence")
+            .field("steps", &self.steps.len())
+            .finish()
+    }
+}
+
+/// A [`CommandSequence`] that is running
+///
+/// Dropping this cancels the sequence. Any command that the sequence is holding down is
+/// released. Held commands are also released when the plugin is disabled, after
+/// [`Plugin::disable`](crate::plugin::Plugin::disable) returns.
+pub struct RunningSequence {
+    /// The remaining steps, shared with the flight loop callback
+    state: Rc<RefCell<SequenceState>>,
+    /// The flight loop that runs the steps
+    flight_loop: FlightLoop<()>,
+}
+
+struct SequenceState {
+    /// The steps that have not started yet
+    steps: VecDeque<SequenceStep>,
+    /// The step that is holding a command or waiting, if any
+    active: Option<ActiveStep>,
+    cancelled: bool,
+}
+
+/// A step that is in progress
+enum ActiveStep {
+    /// Holding a command until the elapsed time reaches the end
+    ///
+    /// The hold is tracked with the other owned holds, so it is released if the plugin is
+    /// disabled while the sequence is running.
+    Holding {
+        /// Releases the command when dropped
+        hold: OwnedCommandHold,
+        /// When to release the command
+        end: Duration,
+    },
+    /// Waiting until the elapsed time reaches the end
+    Waiting(Duration),
+}
+
+/// What the flight loop does next for a running sequence
+enum SequenceAction {
+    /// Waits for the next flight loop cycle
+    Wait,
+    /// Stops the flight loop, because the sequence has finished or been cancelled
+    Stop,
+    /// Triggers a command once
+    Trigger(XPLMCommandRef),
+    /// Holds a command down until the elapsed time reaches the end
+    Begin(XPLMCommandRef, Duration),
+    /// Releases a command that has been held for long enough
+    Release(OwnedCommandHold),
+}
+
+impl RunningSequence {
+    /// Stops the sequence, releasing any command that it is holding down.
+    pub fn cancel(&mut self) {
+        let active = self.state.borrow_mut().cancel();
+        drop(active);
+        self.flight_loop.deactivate();
+    }
+
+    /// Returns true if the sequence has not finished or been cancelled.
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        let state = self.state.borrow();
+        !state.cancelled && (state.active.is_some() || !state.steps.is_empty())
+    }
+
+    /// Returns true if the sequence was cancelled before it finished.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.borrow().cancelled
+    }
+
+    /// Returns the number of steps that have not started yet.
+    #[must_use]
+    pub fn remaining_steps(&self) -> usize {
+        self.state.borrow().steps.len()
+    }
+}
+
+impl Drop for RunningSequence {
+    fn drop(&mut self) {
+        let active = self.state.borrow_mut().cancel();
+        drop(active);
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for RunningSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunningSequence")
+            .field("running", &self.is_running())
+            .field("remaining_steps", &self.remaining_steps())
+            .finish()
+    }
+}
+
+impl SequenceState {
+    /// Runs steps until one needs a command or needs to wait, and returns what to do next.
+    ///
+    /// This does not call X-Plane, so that command handlers can use the sequence.
+    fn advance(&mut self, now: Duration) -> SequenceAction {
+        if self.cancelled {
+            return SequenceAction::Stop;
+        }
+        loop {
+            if let Some(ActiveStep::Holding { end, .. } | ActiveStep::Waiting(end)) = self.active {
+                if now < end {
+                    return SequenceAction::Wait;
+                }
+            }
+            if let Some(ActiveStep::Holding { hold, .. }) = self.active.take() {
+                return SequenceAction::Release(hold);
+            }
+            let Some(step) = self.steps.pop_front() else {
+                return SequenceAction::Stop;
+            };
+            match step {
+                SequenceStep::Trigger(command) => return SequenceAction::Trigger(command),
+                SequenceStep::Hold(command, duration) => {
+                    return SequenceAction::Begin(command, now + duration);
+                }
+                SequenceStep::Wait(duration) => {
+                    self.active = Some(ActiveStep::Waiting(now + duration));
+                }
+                SequenceStep::WaitUntil(mut condition) => {
+                    if !condition() {
+                        self.steps.push_front(SequenceStep::WaitUntil(condition));
+                        return SequenceAction::Wait;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drops the remaining steps, and returns the active step so that any held command can be
+    /// released after the state is no longer borrowed
+    fn cancel(&mut self) -> Option<ActiveStep> {
+        let running = self.active.is_some() || !self.steps.is_empty();
+        self.steps.clear();
+        self.cancelled |= running;
+        self.active.take()
+    }
+}
+
 /// Errors that can occur when finding a command
 #[derive(Snafu, Debug)]
 #[snafu(module)]
@@ -188,6 +726,210 @@ pub trait CommandHandler: 'static {
     fn command_end(&mut self, x: &mut XPAPI) -> CommandHandlerResult;
 }
 
+/// A [`CommandHandler`] that calls a closure in one phase of a command
+struct PhaseHandler<F> {
+    /// The phase to call the closure in
+    phase: XPLMCommandPhase,
+    callback: F,
+}
+
+impl<F: FnMut(&mut XPAPI) + 'static> PhaseHandler<F> {
+    fn new(phase: XPLMCommandPhase, callback: F) -> Self {
+        PhaseHandler { phase, callback }
+    }
+
+    fn call(&mut self, x: &mut XPAPI, phase: XPLMCommandPhase) -> CommandHandlerResult {
+        if phase == self.phase {
+            (self.callback)(x);
+        }
+        CommandHandlerResult::Irrelevant
+    }
+}
+
+impl<F: FnMut(&mut XPAPI) + 'static> CommandHandler for PhaseHandler<F> {
+    fn command_begin(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        self.call(x, XPLMCommandPhase::Begin)
+    }
+    fn command_continue(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        self.call(x, XPLMCommandPhase::Continue)
+    }
+    fn command_end(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        self.call(x, XPLMCommandPhase::End)
+    }
+}
+
+/// A [`CommandHandler`] that repeats a closure while a command is held down, like a key
+/// that repeats on a keyboard
+///
+/// The closure is called when the command is pressed. If the command is still held after
+/// the delay, the closure is called again, and then every interval until the command is
+/// released. X-Plane only sends held commands once per frame, so the closure is called at
+/// most once per frame.
+pub struct HoldHandler<F> {
+    callback: F,
+    /// The time between repeats
+    interval: Duration,
+    /// The time before the first repeat
+    delay: Duration,
+    /// The elapsed time at which the closure should be called next, if the command is held
+    next: Option<Duration>,
+}
+
+impl<F: FnMut(&mut XPAPI) + 'static> HoldHandler<F> {
+    /// Creates a handler that calls `callback` when the command is pressed, and then every
+    /// `interval` while it is held down.
+    pub fn new(interval: Duration, callback: F) -> Self {
+        HoldHandler {
+            callback,
+            interval,
+            delay: interval,
+            next: None,
+        }
+    }
+
+    /// Sets the time between the command being pressed and the first repeat.
+    ///
+    /// By default, this is the same as the interval.
+    #[must_use]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Returns the time between repeats.
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the time before the first repeat.
+    #[must_use]
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+impl<F: FnMut(&mut XPAPI) + 'static> CommandHandler for HoldHandler<F> {
+    fn command_begin(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        (self.callback)(x);
+        self.next = Some(elapsed_time() + self.delay);
+        CommandHandlerResult::Irrelevant
+    }
+    fn command_continue(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        let now = elapsed_time();
+        if self.next.is_some_and(|next| now >= next) {
+            (self.callback)(x);
+            self.next = Some(now + self.interval);
+        }
+        CommandHandlerResult::Irrelevant
+    }
+    fn command_end(&mut self, _x: &mut XPAPI) -> CommandHandlerResult {
+        self.next = None;
+        CommandHandlerResult::Irrelevant
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<F> fmt::Debug for HoldHandler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HoldHandler")
+            .field("interval", &self.interval)
+            .field("delay", &self.delay)
+            .field("held", &self.next.is_some())
+            .finish()
+    }
+}
+
+/// A [`CommandHandler`] that tells short presses of a command from long presses
+///
+/// If the command is released before the threshold, the short press closure is called on
+/// release. If the command is held for at least the threshold, the long press closure is
+/// called as soon as the threshold is reached, without waiting for the release.
+pub struct PressClassifier<S, L> {
+    /// The time a command must be held to be a long press
+    threshold: Duration,
+    short: S,
+    long: L,
+    /// The elapsed time when the command was pressed, if it is held
+    pressed_at: Option<Duration>,
+    /// True if the long press closure has been called for the current press
+    long_called: bool,
+}
+
+impl<S, L> PressClassifier<S, L>
+where
+    S: FnMut(&mut XPAPI) + 'static,
+    L: FnMut(&mut XPAPI) + 'static,
+{
+    /// Creates a classifier that calls `short` for presses shorter than `threshold`,
+    /// and `long` for presses of at least `threshold`.
+    pub fn new(threshold: Duration, short: S, long: L) -> Self {
+        PressClassifier {
+            threshold,
+            short,
+            long,
+            pressed_at: None,
+            long_called: false,
+        }
+    }
+
+    /// Returns the time a command must be held to be a long press.
+    #[must_use]
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Calls the long press closure if the command has been held long enough
+    fn check_long(&mut self, x: &mut XPAPI) {
+        let Some(pressed_at) = self.pressed_at else {
+            return;
+        };
+        if !self.long_called && elapsed_time().saturating_sub(pressed_at) >= self.threshold {
+            self.long_called = true;
+            (self.long)(x);
+        }
+    }
+}
+
+impl<S, L> CommandHandler for PressClassifier<S, L>
+where
+    S: FnMut(&mut XPAPI) + 'static,
+    L: FnMut(&mut XPAPI) + 'static,
+{
+    fn command_begin(&mut self, _x: &mut XPAPI) -> CommandHandlerResult {
+        self.pressed_at = Some(elapsed_time());
+        self.long_called = false;
+        CommandHandlerResult::Irrelevant
+    }
+    fn command_continue(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        self.check_long(x);
+        CommandHandlerResult::Irrelevant
+    }
+    fn command_end(&mut self, x: &mut XPAPI) -> CommandHandlerResult {
+        self.check_long(x);
+        if self.pressed_at.take().is_some() && !self.long_called {
+            (self.short)(x);
+        }
+        CommandHandlerResult::Irrelevant
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<S, L> fmt::Debug for PressClassifier<S, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PressClassifier")
+            .field("threshold", &self.threshold)
+            .field("held", &self.pressed_at.is_some())
+            .finish()
+    }
+}
+
+/// Returns the time since X-Plane started
+fn elapsed_time() -> Duration {
+    let seconds = unsafe { XPLMGetElapsedTime() };
+    Duration::try_from_secs_f32(seconds).unwrap_or_default()
+}
+
 /// A command created by this plugin that can be triggered by other components
 pub struct RegisteredCommandHandler {
     /// The heap-allocated data
@@ -269,6 +1011,234 @@ unsafe extern "C-unwind" fn command_handler(
     }
 }
 
+/// Trait for things that can intercept [`Commands`](Command)
+///
+/// Closures taking `&mut XPAPI` and `&mut InterceptContext` can be used as interceptors.
+pub trait CommandInterceptor: 'static {
+    /// Called in each phase of the command, before X-Plane handles it
+    fn intercept(&mut self, x: &mut XPAPI, context: &mut InterceptContext) -> CommandHandlerResult;
+}
+
+impl<F> CommandInterceptor for F
+where
+    F: FnMut(&mut XPAPI, &mut InterceptContext) -> CommandHandlerResult + 'static,
+{
+    fn intercept(&mut self, x: &mut XPAPI, context: &mut InterceptContext) -> CommandHandlerResult {
+        self(x, context)
+    }
+}
+
+/// Information about an intercepted command, passed to a [`CommandInterceptor`]
+pub struct InterceptContext<'a> {
+    phase: XPLMCommandPhase,
+    held_for: Duration,
+    chain: &'a InterceptChain,
+}
+
+impl<'a> InterceptContext<'a> {
+    /// Returns the phase of the command: begin, continue or end.
+    #[must_use]
+    pub fn phase(&self) -> XPLMCommandPhase {
+        self.phase
+    }
+
+    /// Returns how long the command has been held down in the current press.
+    ///
+    /// This is zero when the command begins.
+    #[must_use]
+    pub fn held_for(&self) -> Duration {
+        self.held_for
+    }
+
+    /// Returns the name of the command.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.chain.name
+    }
+
+    /// Returns a handle to the command.
+    #[must_use]
+    pub fn command(&self) -> Command {
+        Command {
+            id: self.chain.command,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Triggers the command once, without calling any of its interceptors
+    ///
+    /// X-Plane and other plugins handle the command as usual. This is useful for running
+    /// the original behavior of a command after doing some work first.
+    pub fn reissue(&mut self) {
+        // The interceptors are bypassed while they are running.
+        unsafe {
+            XPLMCommandOnce(self.chain.command);
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<'a> fmt::Debug for InterceptContext<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterceptContext")
+            .field("phase", &self.phase)
+            .field("held_for", &self.held_for)
+            .field("name", &self.chain.name)
+            .finish()
+    }
+}
+
+/// An interceptor registered for a command
+///
+/// The interceptor is removed when this is dropped.
+pub struct Interception {
+    chain: Rc<InterceptChain>,
+    /// The ID of the interceptor in its chain
+    id: u64,
+}
+
+/// An interceptor that can be called while its chain is changed
+type SharedInterceptor = Rc<RefCell<dyn CommandInterceptor>>;
+
+/// The interceptors of one command, used as a refcon
+struct InterceptChain {
+    command: XPLMCommandRef,
+    name: String,
+    /// The interceptors with their IDs, in the order they run
+    interceptors: RefCell<Vec<(u64, SharedInterceptor)>>,
+    next_id: Cell<u64>,
+    /// The elapsed time when the current press began
+    pressed_at: Cell<Option<Duration>>,
+    /// True while the interceptors are running. The command bypasses them while this is set,
+    /// so that interceptors can trigger it again.
+    running: Cell<bool>,
+}
+
+impl Interception {
+    fn new(command: XPLMCommandRef, name: &str, interceptor: impl CommandInterceptor) -> Self {
+        let existing = INTERCEPTS.with_borrow(|chains| {
+            chains
+                .iter()
+                .find(|chain| chain.command == command)
+                .cloned()
+        });
+        let chain = existing.unwrap_or_else(|| {
+            let chain = Rc::new(InterceptChain {
+                command,
+                name: name.to_owned(),
+                interceptors: RefCell::new(Vec::new()),
+                next_id: Cell::new(0),
+                pressed_at: Cell::new(None),
+                running: Cell::new(false),
+            });
+            unsafe {
+                XPLMRegisterCommandHandler(
+                    command,
+                    Some(intercept_handler),
+                    1,
+                    Rc::as_ptr(&chain).cast_mut().cast(),
+                );
+            }
+            INTERCEPTS.with_borrow_mut(|chains| chains.push(Rc::clone(&chain)));
+            chain
+        });
+        let id = chain.next_id.get();
+        chain.next_id.set(id + 1);
+        chain
+            .interceptors
+            .borrow_mut()
+            .push((id, Rc::new(RefCell::new(interceptor))));
+        Interception { chain, id }
+    }
+
+    /// Returns the name of the intercepted command.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.chain.name
+    }
+}
+
+impl Drop for Interception {
+    fn drop(&mut self) {
+        let mut interceptors = self.chain.interceptors.borrow_mut();
+        interceptors.retain(|(id, _)| *id != self.id);
+        if interceptors.is_empty() {
+            unsafe {
+                XPLMUnregisterCommandHandler(
+                    self.chain.command,
+                    Some(intercept_handler),
+                    1,
+                    Rc::as_ptr(&self.chain).cast_mut().cast(),
+                );
+            }
+            INTERCEPTS.with_borrow_mut(|chains| {
+                chains.retain(|chain| !Rc::ptr_eq(chain, &self.chain));
+            });
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for Interception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interception")
+            .field("name", &self.chain.name)
+            .finish()
+    }
+}
+
+/// Command handler callback for intercepted commands
+unsafe extern "C-unwind" fn intercept_handler(
+    _: XPLMCommandRef,
+    phase: XPLMCommandPhase,
+    refcon: *mut c_void,
+) -> c_int {
+    let chain = refcon.cast::<InterceptChain>().cast_const();
+    // Keep the chain alive, in case an interceptor drops its Interception.
+    let chain = unsafe {
+        Rc::increment_strong_count(chain);
+        Rc::from_raw(chain)
+    };
+    if chain.running.get() {
+        return 1;
+    }
+    let now = elapsed_time();
+    if phase == XPLMCommandPhase::Begin {
+        chain.pressed_at.set(Some(now));
+    }
+    let held_for = chain
+        .pressed_at
+        .get()
+        .map_or(Duration::ZERO, |pressed_at| now.saturating_sub(pressed_at));
+    if phase == XPLMCommandPhase::End {
+        chain.pressed_at.set(None);
+    }
+    // Interceptors can be added or removed while the chain runs.
+    let interceptors: Vec<_> = chain
+        .interceptors
+        .borrow()
+        .iter()
+        .map(|(_, interceptor)| Rc::clone(interceptor))
+        .collect();
+    let mut x = make_x();
+    let mut context = InterceptContext {
+        phase,
+        held_for,
+        chain: &chain,
+    };
+    chain.running.set(true);
+    let mut allow = 1;
+    for interceptor in interceptors {
+        let result = interceptor.borrow_mut().intercept(&mut x, &mut context);
+        if let CommandHandlerResult::DisallowXPlaneProcessing = result {
+            allow = 0;
+            break;
+        }
+    }
+    chain.running.set(false);
+    allow
+}
+
 /// Errors that can occur when creating a Command
 #[derive(Snafu, Debug)]
 #[snafu(module)]
@@ -293,10 +1263,8 @@ pub enum CommandCreateError {
 mod tests {
 
     use std::{
-        cell::RefCell,
-        ffi::CStr,
+        ffi::{c_float, CStr},
         ptr::{self, NonNull},
-        rc::Rc,
     };
 
     use super::*;
@@ -429,6 +1397,412 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_closure_handlers() {
+        let refcons = Rc::new(RefCell::new(Vec::new()));
+        let refcons_1 = refcons.clone();
+        let register_handler_ctx = xplane_sys::XPLMRegisterCommandHandler_context();
+        register_handler_ctx
+            .expect()
+            .times(4)
+            .returning_st(move |_, _, before, refcon| {
+                assert_eq!(before, 0);
+                refcons_1.borrow_mut().push(refcon);
+            });
+        let unregister_handler_ctx = xplane_sys::XPLMUnregisterCommandHandler_context();
+        unregister_handler_ctx
+            .expect()
+            .times(4)
+            .returning_st(|_, _, _, _| ());
+        let time = Rc::new(RefCell::new(0.0f32));
+        let time_1 = time.clone();
+        let elapsed_ctx = xplane_sys::XPLMGetElapsedTime_context();
+        elapsed_ctx.expect().returning_st(move || *time_1.borrow());
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut cmd = Command {
+            id: NonNull::<c_void>::dangling().as_ptr(),
+            _phantom: PhantomData,
+        };
+        let events_1 = events.clone();
+        let _press = cmd.on_press(move |_| events_1.borrow_mut().push("press"));
+        let events_1 = events.clone();
+        let _release = cmd.on_release(move |_| events_1.borrow_mut().push("release"));
+        let events_1 = events.clone();
+        let _hold = cmd.handle(
+            HoldHandler::new(Duration::from_millis(100), move |_| {
+                events_1.borrow_mut().push("repeat");
+            })
+            .with_delay(Duration::from_millis(500)),
+            false,
+        );
+        let events_1 = events.clone();
+        let events_2 = events.clone();
+        let _classifier = cmd.handle(
+            PressClassifier::new(
+                Duration::from_secs(1),
+                move |_| events_1.borrow_mut().push("short"),
+                move |_| events_2.borrow_mut().push("long"),
+            ),
+            false,
+        );
+
+        let send = |phase, at: f32| {
+            *time.borrow_mut() = at;
+            for refcon in refcons.borrow().iter() {
+                let result = unsafe { command_handler(ptr::null_mut(), phase, *refcon) };
+                assert_eq!(result, 1);
+            }
+        };
+
+        send(XPLMCommandPhase::Begin, 10.0);
+        send(XPLMCommandPhase::Continue, 10.25);
+        send(XPLMCommandPhase::End, 10.5);
+        // The press was too short to repeat
+        assert_eq!(events.take(), ["press", "repeat", "release", "short"]);
+
+        send(XPLMCommandPhase::Begin, 20.0);
+        send(XPLMCommandPhase::Continue, 20.5);
+        send(XPLMCommandPhase::Continue, 20.55);
+        send(XPLMCommandPhase::Continue, 20.625);
+        send(XPLMCommandPhase::Continue, 21.0);
+        send(XPLMCommandPhase::Continue, 21.5);
+        send(XPLMCommandPhase::End, 22.0);
+        assert_eq!(
+            events.take(),
+            ["press", "repeat", "repeat", "repeat", "repeat", "long", "repeat", "release"]
+        );
+    }
+
+    struct FakeData(Rc<RefCell<f32>>);
+
+    impl DataRead<f32> for FakeData {
+        fn get(&self) -> f32 {
+            *self.0.borrow()
+        }
+    }
+
+    type LoopCallback =
+        unsafe extern "C-unwind" fn(c_float, c_float, c_int, *mut c_void) -> c_float;
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::too_many_lines)] // This function has to set up several mocks.
+    fn test_command_sequence() {
+        let loop_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let loop_cell: Rc<RefCell<Option<(LoopCallback, *mut c_void)>>> =
+            Rc::new(RefCell::new(None));
+        let loop_cell_1 = loop_cell.clone();
+        let create_ctx = xplane_sys::XPLMCreateFlightLoop_context();
+        create_ctx.expect().times(4).returning_st(move |s| {
+            let s = unsafe { *s };
+            *loop_cell_1.borrow_mut() = Some((s.callbackFunc.unwrap(), s.refcon));
+            loop_ptr
+        });
+        let schedule_ctx = xplane_sys::XPLMScheduleFlightLoop_context();
+        schedule_ctx.expect().returning_st(|_, _, _| ());
+        let destroy_ctx = xplane_sys::XPLMDestroyFlightLoop_context();
+        destroy_ctx.expect().times(4).returning_st(|_| ());
+        let time = Rc::new(RefCell::new(0.0f32));
+        let time_1 = time.clone();
+        let elapsed_ctx = xplane_sys::XPLMGetElapsedTime_context();
+        elapsed_ctx.expect().returning_st(move || *time_1.borrow());
+        let events = Rc::new(RefCell::new(Vec::new()));
+        // A sequence that command handlers use while its flight loop runs
+        let hooked: Rc<RefCell<Option<RunningSequence>>> = Rc::new(RefCell::new(None));
+        let events_1 = events.clone();
+        let once_ctx = xplane_sys::XPLMCommandOnce_context();
+        once_ctx
+            .expect()
+            .returning_st(move |cmd| events_1.borrow_mut().push(("once", cmd as usize)));
+        let events_1 = events.clone();
+        let hooked_1 = hooked.clone();
+        let begin_ctx = xplane_sys::XPLMCommandBegin_context();
+        begin_ctx.expect().returning_st(move |cmd| {
+            events_1.borrow_mut().push(("begin", cmd as usize));
+            if let Some(sequence) = hooked_1.borrow_mut().as_mut() {
+                assert!(sequence.is_running());
+                sequence.cancel();
+            }
+        });
+        let events_1 = events.clone();
+        let hooked_1 = hooked.clone();
+        let end_ctx = xplane_sys::XPLMCommandEnd_context();
+        end_ctx.expect().returning_st(move |cmd| {
+            events_1.borrow_mut().push(("end", cmd as usize));
+            if let Some(sequence) = hooked_1.borrow().as_ref() {
+                events_1
+                    .borrow_mut()
+                    .push(("remaining", sequence.remaining_steps()));
+            }
+        });
+
+        let command = |id: usize| Command {
+            id: ptr::null_mut::<u8>().wrapping_add(id).cast(),
+            _phantom: PhantomData,
+        };
+        let n2 = Rc::new(RefCell::new(0.0));
+        let mut sequence = CommandSequence::new()
+            .trigger(&command(1))
+            .hold_for(&command(2), Duration::from_secs(3))
+            .wait_until(FakeData(n2.clone()), |n2| n2 > 50.0)
+            .wait(Duration::from_millis(500))
+            .trigger(&command(3))
+            .start(FlightLoopPhase::BeforeFlightModel);
+        let run_loop = |at: f32| {
+            *time.borrow_mut() = at;
+            let (callback, refcon) = loop_cell.borrow().unwrap();
+            unsafe { callback(0.0, 0.0, 0, refcon) }
+        };
+
+        assert_eq!(run_loop(10.0), -1.0);
+        assert_eq!(events.take(), [("once", 1), ("begin", 2)]);
+        run_loop(12.0);
+        assert!(events.borrow().is_empty());
+        run_loop(13.0);
+        assert_eq!(events.take(), [("end", 2)]);
+        run_loop(14.0);
+        *n2.borrow_mut() = 55.0;
+        run_loop(15.0);
+        assert_eq!(sequence.remaining_steps(), 1);
+        assert_eq!(run_loop(15.5), 0.0);
+        assert_eq!(events.take(), [("once", 3)]);
+        assert!(!sequence.is_running());
+        sequence.cancel();
+        assert!(!sequence.is_cancelled());
+        drop(sequence);
+
+        // Dropping a sequence releases its held command
+        let sequence = CommandSequence::new()
+            .hold_for(&command(4), Duration::from_secs(3))
+            .trigger(&command(5))
+            .start(FlightLoopPhase::AfterFlightModel);
+        run_loop(20.0);
+        assert!(sequence.is_running());
+        drop(sequence);
+        assert_eq!(events.take(), [("begin", 4), ("end", 4)]);
+
+        // Disabling the plugin releases a command held by a running sequence
+        let sequence = CommandSequence::new()
+            .hold_for(&command(6), Duration::from_secs(3))
+            .start(FlightLoopPhase::AfterFlightModel);
+        run_loop(30.0);
+        release_all_holds();
+        assert_eq!(events.take(), [("begin", 6), ("end", 6)]);
+        drop(sequence);
+        assert!(events.borrow().is_empty());
+
+        // A command handler cancels the sequence that is holding its command
+        *hooked.borrow_mut() = Some(
+            CommandSequence::new()
+                .hold_for(&command(7), Duration::from_secs(3))
+                .trigger(&command(8))
+                .start(FlightLoopPhase::BeforeFlightModel),
+        );
+        assert_eq!(run_loop(40.0), 0.0);
+        assert_eq!(events.take(), [("begin", 7), ("end", 7), ("remaining", 0)]);
+        let sequence = hooked.borrow_mut().take().unwrap();
+        assert!(sequence.is_cancelled());
+        drop(sequence);
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_owned_holds() {
+        let loop_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let loop_cell: Rc<RefCell<Option<(LoopCallback, *mut c_void)>>> =
+            Rc::new(RefCell::new(None));
+        let loop_cell_1 = loop_cell.clone();
+        let create_ctx = xplane_sys::XPLMCreateFlightLoop_context();
+        create_ctx.expect().once().return_once_st(move |s| {
+            let s = unsafe { *s };
+            *loop_cell_1.borrow_mut() = Some((s.callbackFunc.unwrap(), s.refcon));
+            loop_ptr
+        });
+        let schedule_ctx = xplane_sys::XPLMScheduleFlightLoop_context();
+        schedule_ctx.expect().times(2).returning_st(|_, _, _| ());
+        let destroy_ctx = xplane_sys::XPLMDestroyFlightLoop_context();
+        destroy_ctx.expect().once().return_once_st(|_| ());
+        let time = Rc::new(RefCell::new(0.0f32));
+        let time_1 = time.clone();
+        let elapsed_ctx = xplane_sys::XPLMGetElapsedTime_context();
+        elapsed_ctx.expect().returning_st(move || *time_1.borrow());
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_1 = events.clone();
+        let begin_ctx = xplane_sys::XPLMCommandBegin_context();
+        begin_ctx
+            .expect()
+            .returning_st(move |cmd| events_1.borrow_mut().push(("begin", cmd as usize)));
+        let events_1 = events.clone();
+        let end_ctx = xplane_sys::XPLMCommandEnd_context();
+        end_ctx
+            .expect()
+            .returning_st(move |cmd| events_1.borrow_mut().push(("end", cmd as usize)));
+
+        let command = |id: usize| Command {
+            id: ptr::null_mut::<u8>().wrapping_add(id).cast(),
+            _phantom: PhantomData,
+        };
+
+        let hold = command(1).hold();
+        assert!(hold.is_held());
+        drop(hold);
+        assert_eq!(events.take(), [("begin", 1), ("end", 1)]);
+
+        let mut second = command(2);
+        let detached = second.hold_down().detach();
+        // The command can be used again while it is held
+        let _ = second.hold();
+        assert_eq!(events.take(), [("begin", 2), ("begin", 2), ("end", 2)]);
+
+        *time.borrow_mut() = 10.0;
+        let timed = command(3).hold();
+        let timed_state = Rc::downgrade(&timed.state);
+        timed.release_after(Duration::from_secs(2));
+        command(4).hold_for(Duration::from_secs(4));
+        assert_eq!(events.take(), [("begin", 3), ("begin", 4)]);
+        let run_loop = |at: f32| {
+            *time.borrow_mut() = at;
+            let (callback, refcon) = loop_cell.borrow().unwrap();
+            unsafe { callback(0.0, 0.0, 0, refcon) }
+        };
+        assert_eq!(run_loop(11.0), -1.0);
+        assert!(events.borrow().is_empty());
+        assert_eq!(run_loop(12.0), -1.0);
+        assert_eq!(events.take(), [("end", 3)]);
+        // Nothing keeps a released timed hold alive
+        assert!(timed_state.upgrade().is_none());
+
+        // The plugin is disabled before the other holds expire
+        release_all_holds();
+        assert_eq!(events.take(), [("end", 2), ("end", 4)]);
+        assert!(!detached.is_held());
+        drop(detached);
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)] // This function has to set up several mocks.
+    fn test_interception() {
+        let command_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_command_context = xplane_sys::XPLMFindCommand_context();
+        find_command_context
+            .expect()
+            .times(2)
+            .returning_st(move |_| command_ptr);
+        let refcon_cell = Rc::new(RefCell::new(ptr::null_mut()));
+        let refcon_cell_1 = refcon_cell.clone();
+        let register_handler_ctx = xplane_sys::XPLMRegisterCommandHandler_context();
+        register_handler_ctx.expect().once().return_once_st(
+            move |cmd_ref, handler, before, refcon| {
+                assert_eq!(cmd_ref, command_ptr);
+                assert!(handler == Some(intercept_handler));
+                assert_eq!(before, 1);
+                *refcon_cell_1.borrow_mut() = refcon;
+            },
+        );
+        let refcon_cell_1 = refcon_cell.clone();
+        let unregister_handler_ctx = xplane_sys::XPLMUnregisterCommandHandler_context();
+        unregister_handler_ctx.expect().once().return_once_st(
+            move |cmd_ref, handler, before, refcon| {
+                assert_eq!(cmd_ref, command_ptr);
+                assert!(handler == Some(intercept_handler));
+                assert_eq!(before, 1);
+                assert_eq!(refcon, *refcon_cell_1.borrow());
+            },
+        );
+        let time = Rc::new(RefCell::new(0.0f32));
+        let time_1 = time.clone();
+        let elapsed_ctx = xplane_sys::XPLMGetElapsedTime_context();
+        elapsed_ctx.expect().returning_st(move || *time_1.borrow());
+        let refcon_cell_1 = refcon_cell.clone();
+        let once_ctx = xplane_sys::XPLMCommandOnce_context();
+        once_ctx.expect().times(3).returning_st(move |cmd_ref| {
+            // Triggering the command from an interceptor does not run the interceptors again.
+            let refcon = *refcon_cell_1.borrow();
+            for phase in [XPLMCommandPhase::Begin, XPLMCommandPhase::End] {
+                assert_eq!(unsafe { intercept_handler(cmd_ref, phase, refcon) }, 1);
+            }
+        });
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut x = make_x();
+        let events_1 = events.clone();
+        let first = x
+            .command
+            .intercept(
+                "sim/electrical/battery_1_on",
+                move |_: &mut XPAPI, context: &mut InterceptContext| {
+                    assert_eq!(context.name(), "sim/electrical/battery_1_on");
+                    events_1
+                        .borrow_mut()
+                        .push(("first", context.phase(), context.held_for()));
+                    if context.phase() == XPLMCommandPhase::End
+                        && context.held_for() < Duration::from_secs(1)
+                    {
+                        context.reissue();
+                        CommandHandlerResult::DisallowXPlaneProcessing
+                    } else {
+                        CommandHandlerResult::AllowXPlaneProcessing
+                    }
+                },
+            )
+            .unwrap();
+        let events_1 = events.clone();
+        let second = x
+            .command
+            .intercept(
+                "sim/electrical/battery_1_on",
+                move |_: &mut XPAPI, context: &mut InterceptContext| {
+                    events_1
+                        .borrow_mut()
+                        .push(("second", context.phase(), context.held_for()));
+                    if context.phase() == XPLMCommandPhase::Begin {
+                        context.command().trigger();
+                    }
+                    CommandHandlerResult::AllowXPlaneProcessing
+                },
+            )
+            .unwrap();
+        assert_eq!(second.name(), "sim/electrical/battery_1_on");
+
+        let send = |phase, at: f32| {
+            *time.borrow_mut() = at;
+            unsafe { intercept_handler(command_ptr, phase, *refcon_cell.borrow()) }
+        };
+        assert_eq!(send(XPLMCommandPhase::Begin, 5.0), 1);
+        assert_eq!(send(XPLMCommandPhase::Continue, 5.25), 1);
+        // The first interceptor stops the second from running
+        assert_eq!(send(XPLMCommandPhase::End, 5.5), 0);
+        assert_eq!(
+            events.take(),
+            [
+                ("first", XPLMCommandPhase::Begin, Duration::ZERO),
+                ("second", XPLMCommandPhase::Begin, Duration::ZERO),
+                (
+                    "first",
+                    XPLMCommandPhase::Continue,
+                    Duration::from_millis(250)
+                ),
+                (
+                    "second",
+                    XPLMCommandPhase::Continue,
+                    Duration::from_millis(250)
+                ),
+                ("first", XPLMCommandPhase::End, Duration::from_millis(500)),
+            ]
+        );
+
+        // The handler stays registered until every interception is dropped
+        drop(first);
+        assert_eq!(send(XPLMCommandPhase::Begin, 8.0), 1);
+        assert_eq!(
+            events.take(),
+            [("second", XPLMCommandPhase::Begin, Duration::ZERO)]
+        );
+        drop(second);
+    }
+
     #[test]
     fn test_command_exists() {
         let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
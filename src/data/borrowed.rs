@@ -3,31 +3,71 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::ffi::c_void;
+#[cfg(feature = "XPLM400")]
+use std::{
+    ffi::{c_int, CStr},
+    mem,
+};
 use std::{
     ffi::{CString, NulError},
-    fmt::Debug,
+    fmt::{self, Debug, Display},
     marker::PhantomData,
     ptr,
 };
 
+use num_enum::TryFromPrimitive;
 use snafu::prelude::*;
 
 use xplane_sys::{
-    XPLMCanWriteDataRef, XPLMDataRef, XPLMFindDataRef, XPLMGetDataRefTypes, XPLMGetDatab,
-    XPLMGetDatad, XPLMGetDataf, XPLMGetDatai, XPLMGetDatavf, XPLMGetDatavi, XPLMSetDatab,
-    XPLMSetDatad, XPLMSetDataf, XPLMSetDatai, XPLMSetDatavf, XPLMSetDatavi,
+    XPLMCanWriteDataRef, XPLMDataRef, XPLMDataTypeID, XPLMFindDataRef, XPLMGetDataRefTypes,
+    XPLMGetDatab, XPLMGetDatad, XPLMGetDataf, XPLMGetDatai, XPLMGetDatavf, XPLMGetDatavi,
+    XPLMSetDatab, XPLMSetDatad, XPLMSetDataf, XPLMSetDatai, XPLMSetDatavf, XPLMSetDatavi,
 };
+#[cfg(feature = "XPLM400")]
+use xplane_sys::{
+    XPLMDataRefInfo_t, XPLMGetDataRefInfo, XPLMGetDataRefsByIndex, XPLMPluginID, XPLM_NO_PLUGIN_ID,
+};
+
+use crate::NoSendSync;
 
-use super::{ArrayRead, ArrayReadWrite, DataRead, DataReadWrite, DataType, ReadOnly, ReadWrite};
+#[cfg(feature = "XPLM400")]
+use super::Access;
+use super::{
+    enums::{self, IntEnum, InvalidValue},
+    has_data_type,
+    owned::{OverflowError, OverflowPolicy},
+    str_bytes, ArrayRead, ArrayReadWrite, DataRead, DataReadWrite, DataType, EnumRead,
+    EnumReadWrite, ReadOnly, ReadWrite, ScalarType, StrRead, StrReadWrite,
+};
 
 /// A dataref created by X-Plane or another plugin
 ///
 /// T is the data type stored in the dataref.
 ///
 /// A is the access level (`ReadOnly` or `ReadWrite`)
+///
+/// When a dataref is found, its types are checked against T:
+///
+/// | T | Dataref type |
+/// |---|---|
+/// | `bool`, `u8`, `i8`, `u16`, `i16`, `u32`, `i32` | int |
+/// | `f32` | float |
+/// | `f64` | double, or float (converted on each read and write) |
+/// | `[i32]`, `[u32]` | int array |
+/// | `[f32]` | float array |
+/// | `[u8]`, `[i8]`, `str` | data |
+/// | `[T; N]` | as for `[T]`, with at least N elements |
+/// | [`IntEnum`] | int |
+/// | [units](super::units) | as for their storage type, `f32` or `f64` |
+///
+/// Integer types other than `i32` are converted with `as`, so values that do not fit are
+/// truncated. A `bool` is true if the int value is not zero.
 pub struct DataRef<T: ?Sized, A = ReadOnly> {
     /// The dataref handle
     pub(super) id: XPLMDataRef,
+    /// True if `T` is read and written as a double, but the dataref only has a float value.
+    /// This is decided once when the dataref is found.
+    pub(super) float_fallback: bool,
     /// Type and data access phantom data
     pub(super) _phantom: PhantomData<(*mut (), A, T)>,
 }
@@ -36,22 +76,29 @@ impl<T: DataType + ?Sized> DataRef<T, ReadOnly> {
     pub(super) fn find<S: AsRef<str>>(name: S) -> Result<Self, FindError> {
         let name = name.as_ref();
         let name_c = CString::new(name)?;
-        let expected_type = T::sim_type();
 
         let dataref = unsafe { XPLMFindDataRef(name_c.as_ptr()) };
         if dataref.is_null() {
             return Err(FindError::NotFound);
         }
 
+        Self::from_id(dataref)
+    }
+
+    /// Wraps an existing, non-null dataref handle, checking that its type matches `T`.
+    fn from_id(dataref: XPLMDataRef) -> Result<Self, FindError> {
+        let expected_type = T::sim_type();
         let actual_type = unsafe { XPLMGetDataRefTypes(dataref) };
-        if actual_type & expected_type == expected_type {
-            Err(FindError::WrongType)
-        } else {
-            Ok(DataRef {
-                id: dataref,
-                _phantom: PhantomData,
-            })
+        ensure!(compatible(actual_type, expected_type), WrongTypeSnafu);
+        if let Some(expected) = T::fixed_len() {
+            let actual = array_len(dataref, expected_type);
+            ensure!(actual >= expected, WrongLengthSnafu { expected, actual });
         }
+        Ok(DataRef {
+            id: dataref,
+            float_fallback: expected_type == XPLMDataTypeID::Double && !actual_type.double(),
+            _phantom: PhantomData,
+        })
     }
 
     /// Makes this dataref writable
@@ -62,6 +109,7 @@ impl<T: DataType + ?Sized> DataRef<T, ReadOnly> {
         if writable {
             Ok(DataRef {
                 id: self.id,
+                float_fallback: self.float_fallback,
                 _phantom: PhantomData,
             })
         } else {
@@ -70,6 +118,45 @@ impl<T: DataType + ?Sized> DataRef<T, ReadOnly> {
     }
 }
 
+impl<T: DataType + ?Sized> DataRef<T, ReadWrite> {
+    pub(super) fn find_writable<S: AsRef<str>>(name: S) -> Result<Self, FindError> {
+        DataRef::find(name)?
+            .writeable()
+            .map_err(|_| FindError::NotWritable)
+    }
+}
+
+/// Returns true if a dataref with the types `actual` can be accessed as `expected`
+fn compatible(actual: XPLMDataTypeID, expected: XPLMDataTypeID) -> bool {
+    // Doubles are also read from and written to float-only datarefs, with conversion.
+    actual & expected == expected || (expected == XPLMDataTypeID::Double && actual.float())
+}
+
+impl<A> DataRead<f64> for DataRef<f64, A> {
+    fn get(&self) -> f64 {
+        if self.float_fallback {
+            f64::from(unsafe { XPLMGetDataf(self.id) })
+        } else {
+            unsafe { XPLMGetDatad(self.id) }
+        }
+    }
+}
+
+impl DataReadWrite<f64> for DataRef<f64, ReadWrite> {
+    #[allow(clippy::cast_possible_truncation)]
+    fn set(&mut self, value: f64) {
+        if self.float_fallback {
+            unsafe {
+                XPLMSetDataf(self.id, value as f32);
+            }
+        } else {
+            unsafe {
+                XPLMSetDatad(self.id, value);
+            }
+        }
+    }
+}
+
 #[allow(clippy::missing_fields_in_debug)]
 impl<T: ?Sized, A> Debug for DataRef<T, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -81,6 +168,44 @@ impl<T: ?Sized, A> Debug for DataRef<T, A> {
 
 /// Creates a `DataType` implementation, `DataRef::get` and `DataRef::set` for a type
 macro_rules! dataref_type {
+    // Fixed-length array case
+    (
+        $(#[$meta:meta])*
+        native [$native_type:ty; N];
+        sim $sim_type:ident as [$sim_native_type:ty];
+        read $read_fn:ident;
+        write $write_fn:ident;
+    ) => {
+        impl<const N: usize, A> DataRead<[$native_type; N]> for DataRef<[$native_type; N], A> {
+            fn get(&self) -> [$native_type; N] {
+                let mut values = [<$native_type>::default(); N];
+                unsafe {
+                    $read_fn(
+                        self.id,
+                        values.as_mut_ptr().cast::<$sim_native_type>(),
+                        0,
+                        array_size(N),
+                    );
+                }
+                values
+            }
+        }
+        impl<const N: usize> DataReadWrite<[$native_type; N]>
+            for DataRef<[$native_type; N], ReadWrite>
+        {
+            fn set(&mut self, value: [$native_type; N]) {
+                unsafe {
+                    // Cast to *mut because the API requires it
+                    $write_fn(
+                        self.id,
+                        value.as_ptr().cast::<$sim_native_type>().cast_mut(),
+                        0,
+                        array_size(N),
+                    );
+                }
+            }
+        }
+    };
     // Array case
     (
         $(#[$meta:meta])*
@@ -92,13 +217,13 @@ macro_rules! dataref_type {
         write $write_fn:ident;
     ) => {
         impl<A> ArrayRead<[$native_type]> for DataRef<[$native_type], A> {
-            fn get(&self, dest: &mut [$native_type]) -> usize {
+            fn get_range(&self, offset: usize, dest: &mut [$native_type]) -> usize {
                 let size = array_size(dest.len());
                 let copy_count = unsafe {
                     $read_fn(
                         self.id,
                         dest.as_mut_ptr().cast::<$sim_native_type>(),
-                        0,
+                        array_size(offset),
                         size,
                     )
                 };
@@ -111,20 +236,38 @@ macro_rules! dataref_type {
         }
 
         impl ArrayReadWrite<[$native_type]> for DataRef<[$native_type], ReadWrite> {
-            fn set(&mut self, values: &[$native_type]) {
+            fn set_range(&mut self, offset: usize, values: &[$native_type]) {
                 let size = array_size(values.len());
                 unsafe {
                     // Cast to *mut because the API requires it
                     $write_fn(
                         self.id,
                         values.as_ptr().cast::<$sim_native_type>().cast_mut(),
-                        0,
+                        array_size(offset),
                         size,
                     );
                 }
             }
         }
     };
+    // Newtype case, which accesses the dataref as the inner type
+    (
+        $(#[$meta:meta])*
+        native $native_type:ident($inner_type:ty);
+    ) => {
+        impl<A> DataRead<$native_type<$inner_type>> for DataRef<$native_type<$inner_type>, A> {
+            fn get(&self) -> $native_type<$inner_type> {
+                $native_type(self.cast::<$inner_type, A>().get())
+            }
+        }
+        impl DataReadWrite<$native_type<$inner_type>>
+            for DataRef<$native_type<$inner_type>, ReadWrite>
+        {
+            fn set(&mut self, value: $native_type<$inner_type>) {
+                self.cast::<$inner_type, ReadWrite>().set(value.0);
+            }
+        }
+    };
     // Basic case
     (
         $(#[$meta:meta])*
@@ -146,6 +289,8 @@ macro_rules! dataref_type {
     };
 }
 
+pub(super) use dataref_type;
+
 dataref_type! {
     native u8;
     sim xplmType_Int as i32;
@@ -196,13 +341,6 @@ dataref_type! {
     write XPLMSetDataf;
 }
 
-dataref_type! {
-    native f64;
-    sim xplmType_Double as f64;
-    read XPLMGetDatad;
-    write XPLMSetDatad;
-}
-
 dataref_type! {
     native [i32];
     sim xplmType_IntArray as [i32];
@@ -238,6 +376,27 @@ dataref_type! {
     write XPLMSetDatab;
 }
 
+dataref_type! {
+    native [i32; N];
+    sim xplmType_IntArray as [i32];
+    read XPLMGetDatavi;
+    write XPLMSetDatavi;
+}
+
+dataref_type! {
+    native [f32; N];
+    sim xplmType_FloatArray as [f32];
+    read XPLMGetDatavf;
+    write XPLMSetDatavf;
+}
+
+dataref_type! {
+    native [u8; N];
+    sim xplmType_Data as [c_void];
+    read XPLMGetDatab;
+    write XPLMSetDatab;
+}
+
 impl<A> DataRead<bool> for DataRef<bool, A> {
     fn get(&self) -> bool {
         let int_value = unsafe { XPLMGetDatai(self.id) };
@@ -254,6 +413,599 @@ impl DataReadWrite<bool> for DataRef<bool, ReadWrite> {
     }
 }
 
+impl<T: ?Sized, A> DataRef<T, A> {
+    /// Returns a handle to the same dataref, accessed as `U`
+    pub(super) fn cast<U: ?Sized, B>(&self) -> DataRef<U, B> {
+        DataRef {
+            id: self.id,
+            float_fallback: self.float_fallback,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A> DataRef<str, A> {
+    /// Returns this dataref as a byte array
+    fn bytes(&self) -> DataRef<[u8], A> {
+        self.cast()
+    }
+}
+
+impl<A> StrRead for DataRef<str, A> {
+    fn get_bytes(&self) -> Vec<u8> {
+        self.bytes().as_vec()
+    }
+    fn max_len(&self) -> usize {
+        self.bytes().len()
+    }
+}
+
+impl StrReadWrite for DataRef<str, ReadWrite> {
+    fn set_with_policy(
+        &mut self,
+        value: &str,
+        policy: OverflowPolicy,
+    ) -> Result<(), OverflowError> {
+        // Datarefs from X-Plane and other plugins cannot grow
+        let policy = match policy {
+            OverflowPolicy::Grow => OverflowPolicy::Error,
+            policy => policy,
+        };
+        let mut bytes = self.bytes();
+        let value = str_bytes(value, bytes.len(), policy)?;
+        bytes.set(&value);
+        Ok(())
+    }
+}
+
+impl<E, A> EnumRead<E> for DataRef<IntEnum<E>, A>
+where
+    E: TryFromPrimitive + Into<E::Primitive> + Copy,
+    E::Primitive: ScalarType,
+{
+    fn get(&self) -> Result<E, InvalidValue> {
+        enums::from_raw(self.get_raw())
+    }
+    fn get_raw(&self) -> i32 {
+        unsafe { XPLMGetDatai(self.id) }
+    }
+}
+
+impl<E> EnumReadWrite<E> for DataRef<IntEnum<E>, ReadWrite>
+where
+    E: TryFromPrimitive + Into<E::Primitive> + Copy,
+    E::Primitive: ScalarType,
+{
+    fn set(&mut self, value: E) {
+        unsafe {
+            XPLMSetDatai(self.id, enums::to_raw(value));
+        }
+    }
+}
+
+/// A dataref created by X-Plane or another plugin, with a type only known at runtime
+///
+/// Values are read and written as [`DataValue`]s. When a value of a type that the dataref
+/// does not support natively is requested or written, it is converted if it fits.
+///
+/// A is the access level (`ReadOnly` or `ReadWrite`)
+pub struct AnyDataRef<A = ReadOnly> {
+    /// The dataref handle
+    id: XPLMDataRef,
+    /// The types that this dataref supports, from `XPLMGetDataRefTypes`
+    types: XPLMDataTypeID,
+    /// Data access phantom data
+    _phantom: PhantomData<(NoSendSync, A)>,
+}
+
+impl AnyDataRef<ReadOnly> {
+    pub(super) fn find<S: AsRef<str>>(name: S) -> Result<Self, FindError> {
+        let name_c = CString::new(name.as_ref())?;
+
+        let dataref = unsafe { XPLMFindDataRef(name_c.as_ptr()) };
+        if dataref.is_null() {
+            return Err(FindError::NotFound);
+        }
+
+        let types = unsafe { XPLMGetDataRefTypes(dataref) };
+        if DataValueType::natural(types).is_none() {
+            return Err(FindError::WrongType);
+        }
+        Ok(AnyDataRef {
+            id: dataref,
+            types,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Makes this dataref writable
+    /// # Errors
+    /// Returns Err(self) if the dataref cannot be written.
+    pub fn writeable(self) -> Result<AnyDataRef<ReadWrite>, Self> {
+        let writable = unsafe { XPLMCanWriteDataRef(self.id) == 1 };
+        if writable {
+            Ok(AnyDataRef {
+                id: self.id,
+                types: self.types,
+                _phantom: PhantomData,
+            })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<A> AnyDataRef<A> {
+    /// Returns the bitmask of types this dataref supports.
+    #[must_use]
+    pub fn types(&self) -> XPLMDataTypeID {
+        self.types
+    }
+
+    /// Returns true if this dataref natively supports values of type `value_type`.
+    #[must_use]
+    pub fn supports(&self, value_type: DataValueType) -> bool {
+        value_type.is_in(self.types)
+    }
+
+    /// Returns the type that [`AnyDataRef::get`] reads.
+    ///
+    /// If a dataref supports more than one type, single values are preferred over arrays, and
+    /// more precise types are preferred over less precise ones.
+    /// # Panics
+    /// Panics if the dataref has no recognized type. This is checked when it is found.
+    #[must_use]
+    pub fn natural_type(&self) -> DataValueType {
+        DataValueType::natural(self.types).unwrap() // UNWRAP: Checked in AnyDataRef::find.
+    }
+
+    /// Reads the value of this dataref as its [natural type](AnyDataRef::natural_type).
+    #[must_use]
+    pub fn get(&self) -> DataValue {
+        self.read_native(self.natural_type())
+    }
+
+    /// Reads the value of this dataref as a specific type.
+    ///
+    /// If the dataref does not natively support `value_type`, its natural value is converted.
+    /// # Errors
+    /// Returns an error if the value cannot be represented as `value_type`.
+    pub fn get_as(&self, value_type: DataValueType) -> Result<DataValue, DataValueError> {
+        if self.supports(value_type) {
+            Ok(self.read_native(value_type))
+        } else {
+            self.get().convert(value_type)
+        }
+    }
+
+    /// Returns the number of elements in this dataref, if it is an array.
+    /// Returns [`None`] if the dataref does not support any array type.
+    #[must_use]
+    pub fn array_len(&self) -> Option<usize> {
+        let len = if self.types.float_array() {
+            unsafe { XPLMGetDatavf(self.id, ptr::null_mut(), 0, 0) }
+        } else if self.types.int_array() {
+            unsafe { XPLMGetDatavi(self.id, ptr::null_mut(), 0, 0) }
+        } else if has_data_type(self.types) {
+            unsafe { XPLMGetDatab(self.id, ptr::null_mut(), 0, 0) }
+        } else {
+            return None;
+        };
+        Some(len.try_into().unwrap_or(0))
+    }
+
+    /// Reads a value as a type the dataref supports.
+    fn read_native(&self, value_type: DataValueType) -> DataValue {
+        match value_type {
+            DataValueType::Int => DataValue::Int(unsafe { XPLMGetDatai(self.id) }),
+            DataValueType::Float => DataValue::Float(unsafe { XPLMGetDataf(self.id) }),
+            DataValueType::Double => DataValue::Double(unsafe { XPLMGetDatad(self.id) }),
+            DataValueType::IntArray => {
+                let len = unsafe { XPLMGetDatavi(self.id, ptr::null_mut(), 0, 0) };
+                let mut values = vec![0; len.try_into().unwrap_or(0)];
+                let read = unsafe { XPLMGetDatavi(self.id, values.as_mut_ptr(), 0, len) };
+                values.truncate(read.try_into().unwrap_or(0));
+                DataValue::IntArray(values)
+            }
+            DataValueType::FloatArray => {
+                let len = unsafe { XPLMGetDatavf(self.id, ptr::null_mut(), 0, 0) };
+                let mut values = vec![0.0; len.try_into().unwrap_or(0)];
+                let read = unsafe { XPLMGetDatavf(self.id, values.as_mut_ptr(), 0, len) };
+                values.truncate(read.try_into().unwrap_or(0));
+                DataValue::FloatArray(values)
+            }
+            DataValueType::Bytes => {
+                let len = unsafe { XPLMGetDatab(self.id, ptr::null_mut(), 0, 0) };
+                let mut values = vec![0u8; len.try_into().unwrap_or(0)];
+                let read = unsafe { XPLMGetDatab(self.id, values.as_mut_ptr().cast(), 0, len) };
+                values.truncate(read.try_into().unwrap_or(0));
+                DataValue::Bytes(values)
+            }
+        }
+    }
+}
+
+impl AnyDataRef<ReadWrite> {
+    /// Writes a value to this dataref.
+    ///
+    /// If the dataref supports the type of `value`, it is written as-is. Otherwise, it is
+    /// converted to the [natural type](AnyDataRef::natural_type) of this dataref.
+    ///
+    /// Arrays are written starting at the first element. Elements beyond the length of
+    /// `value` are not changed.
+    /// # Errors
+    /// Returns an error if the value cannot be converted to a type this dataref supports,
+    /// or if an array value is longer than the dataref.
+    pub fn set(&mut self, value: &DataValue) -> Result<(), DataValueError> {
+        let converted;
+        let value = if self.supports(value.value_type()) {
+            value
+        } else {
+            converted = value.clone().convert(self.natural_type())?;
+            &converted
+        };
+        if let Some(len) = value.array_len() {
+            let max = self.array_len().unwrap_or(0);
+            ensure!(len <= max, TooLongSnafu { len, max });
+        }
+        match value {
+            DataValue::Int(v) => unsafe { XPLMSetDatai(self.id, *v) },
+            DataValue::Float(v) => unsafe { XPLMSetDataf(self.id, *v) },
+            DataValue::Double(v) => unsafe { XPLMSetDatad(self.id, *v) },
+            DataValue::IntArray(v) => unsafe {
+                XPLMSetDatavi(self.id, v.as_ptr().cast_mut(), 0, array_size(v.len()));
+            },
+            DataValue::FloatArray(v) => unsafe {
+                XPLMSetDatavf(self.id, v.as_ptr().cast_mut(), 0, array_size(v.len()));
+            },
+            DataValue::Bytes(v) => unsafe {
+                XPLMSetDatab(
+                    self.id,
+                    v.as_ptr().cast_mut().cast(),
+                    0,
+                    array_size(v.len()),
+                );
+            },
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<A> Debug for AnyDataRef<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnyDataRef")
+            .field("id", &"[dataref handle]")
+            .field("types", &self.types)
+            .finish()
+    }
+}
+
+/// The type of a [`DataValue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataValueType {
+    /// A single 32-bit integer
+    Int,
+    /// A single 32-bit float
+    Float,
+    /// A single 64-bit float
+    Double,
+    /// An array of 32-bit integers
+    IntArray,
+    /// An array of 32-bit floats
+    FloatArray,
+    /// An array of bytes
+    Bytes,
+}
+
+impl DataValueType {
+    /// Returns the preferred type out of a type bitmask, if any are recognized.
+    fn natural(types: XPLMDataTypeID) -> Option<Self> {
+        [
+            DataValueType::Double,
+            DataValueType::Float,
+            DataValueType::Int,
+            DataValueType::FloatArray,
+            DataValueType::IntArray,
+            DataValueType::Bytes,
+        ]
+        .into_iter()
+        .find(|value_type| value_type.is_in(types))
+    }
+
+    /// Returns true if this type is part of a type bitmask.
+    fn is_in(self, types: XPLMDataTypeID) -> bool {
+        match self {
+            DataValueType::Int => types.int(),
+            DataValueType::Float => types.float(),
+            DataValueType::Double => types.double(),
+            DataValueType::IntArray => types.int_array(),
+            DataValueType::FloatArray => types.float_array(),
+            DataValueType::Bytes => has_data_type(types),
+        }
+    }
+}
+
+impl Display for DataValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataValueType::Int => "int",
+            DataValueType::Float => "float",
+            DataValueType::Double => "double",
+            DataValueType::IntArray => "int array",
+            DataValueType::FloatArray => "float array",
+            DataValueType::Bytes => "byte array",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A value read from or written to an [`AnyDataRef`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue {
+    /// A single 32-bit integer
+    Int(i32),
+    /// A single 32-bit float
+    Float(f32),
+    /// A single 64-bit float
+    Double(f64),
+    /// An array of 32-bit integers
+    IntArray(Vec<i32>),
+    /// An array of 32-bit floats
+    FloatArray(Vec<f32>),
+    /// An array of bytes
+    Bytes(Vec<u8>),
+}
+
+impl DataValue {
+    /// Returns the type of this value.
+    #[must_use]
+    pub fn value_type(&self) -> DataValueType {
+        match self {
+            DataValue::Int(_) => DataValueType::Int,
+            DataValue::Float(_) => DataValueType::Float,
+            DataValue::Double(_) => DataValueType::Double,
+            DataValue::IntArray(_) => DataValueType::IntArray,
+            DataValue::FloatArray(_) => DataValueType::FloatArray,
+            DataValue::Bytes(_) => DataValueType::Bytes,
+        }
+    }
+
+    /// Returns the number of elements in this value, if it is an array.
+    #[must_use]
+    pub fn array_len(&self) -> Option<usize> {
+        match self {
+            DataValue::Int(_) | DataValue::Float(_) | DataValue::Double(_) => None,
+            DataValue::IntArray(v) => Some(v.len()),
+            DataValue::FloatArray(v) => Some(v.len()),
+            DataValue::Bytes(v) => Some(v.len()),
+        }
+    }
+
+    /// Converts this value to another type.
+    ///
+    /// Numbers are converted between integers and floating-point types as long as they fit.
+    /// Floating-point values must be whole numbers to become integers. Precision may be lost
+    /// when converting a double to a float.
+    /// Numeric arrays are converted element by element. Byte arrays can not be converted.
+    /// # Errors
+    /// Returns an error if the types are incompatible, or if a value does not fit in the
+    /// target type.
+    pub fn convert(self, to: DataValueType) -> Result<DataValue, DataValueError> {
+        let from = self.value_type();
+        if from == to {
+            return Ok(self);
+        }
+        let incompatible = || DataValueError::Incompatible { from, to };
+        let converted = match (self, to) {
+            (DataValue::Int(v), DataValueType::Float) => DataValue::Float(int_to_float(v)?),
+            (DataValue::Int(v), DataValueType::Double) => DataValue::Double(f64::from(v)),
+            (DataValue::Float(v), DataValueType::Int) => DataValue::Int(double_to_int(v.into())?),
+            (DataValue::Float(v), DataValueType::Double) => DataValue::Double(v.into()),
+            (DataValue::Double(v), DataValueType::Int) => DataValue::Int(double_to_int(v)?),
+            (DataValue::Double(v), DataValueType::Float) => DataValue::Float(double_to_float(v)?),
+            (DataValue::IntArray(v), DataValueType::FloatArray) => {
+                DataValue::FloatArray(v.into_iter().map(int_to_float).collect::<Result<_, _>>()?)
+            }
+            (DataValue::FloatArray(v), DataValueType::IntArray) => DataValue::IntArray(
+                v.into_iter()
+                    .map(|f| double_to_int(f.into()))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(incompatible()),
+        };
+        Ok(converted)
+    }
+}
+
+/// Converts an integer to a float, if it can be represented exactly.
+#[allow(clippy::cast_precision_loss, clippy::float_cmp)]
+fn int_to_float(value: i32) -> Result<f32, DataValueError> {
+    let converted = value as f32;
+    // Exact comparison is intended: any difference means precision was lost.
+    ensure!(f64::from(converted) == f64::from(value), OutOfRangeSnafu);
+    Ok(converted)
+}
+
+/// Converts a double to an integer, if it is a whole number within range.
+#[allow(clippy::cast_possible_truncation)]
+fn double_to_int(value: f64) -> Result<i32, DataValueError> {
+    ensure!(
+        value.fract() == 0.0 && value >= f64::from(i32::MIN) && value <= f64::from(i32::MAX),
+        OutOfRangeSnafu
+    );
+    Ok(value as i32)
+}
+
+/// Converts a double to a float, if it is within range.
+#[allow(clippy::cast_possible_truncation)]
+fn double_to_float(value: f64) -> Result<f32, DataValueError> {
+    ensure!(
+        !value.is_finite() || value.abs() <= f64::from(f32::MAX),
+        OutOfRangeSnafu
+    );
+    Ok(value as f32)
+}
+
+/// Errors that can occur when converting or writing a [`DataValue`]
+#[derive(Snafu, Debug)]
+pub enum DataValueError {
+    /// The value can not be converted to the requested type
+    #[snafu(display("Can not convert {from} to {to}"))]
+    Incompatible {
+        /// The type of the value
+        from: DataValueType,
+        /// The requested type
+        to: DataValueType,
+    },
+
+    /// The value does not fit in the requested type
+    #[snafu(display("Value out of range for the requested type"))]
+    OutOfRange,
+
+    /// The array value is longer than the dataref
+    #[snafu(display("Array of length {len} is too long for a dataref of length {max}"))]
+    TooLong {
+        /// The length of the array value
+        len: usize,
+        /// The length of the dataref
+        max: usize,
+    },
+}
+
+/// Information about a dataref registered with X-Plane, as returned by [`DataRefs`].
+#[cfg(feature = "XPLM400")]
+pub struct DataRefInfo {
+    /// The dataref handle
+    id: XPLMDataRef,
+    /// The full name of the dataref
+    name: String,
+    /// The types this dataref can be accessed as
+    types: XPLMDataTypeID,
+    /// Whether the dataref may be written
+    writable: bool,
+    /// The plugin that registered this dataref
+    owner: XPLMPluginID,
+}
+
+#[cfg(feature = "XPLM400")]
+impl DataRefInfo {
+    /// Queries X-Plane for information about a dataref handle.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn from_id(id: XPLMDataRef) -> Self {
+        let mut info = XPLMDataRefInfo_t {
+            structSize: mem::size_of::<XPLMDataRefInfo_t>() as c_int,
+            name: ptr::null(),
+            type_: XPLMDataTypeID::Unknown,
+            writable: 0,
+            owner: XPLM_NO_PLUGIN_ID,
+        };
+        unsafe {
+            XPLMGetDataRefInfo(id, &mut info);
+        }
+        let name = if info.name.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(info.name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        DataRefInfo {
+            id,
+            name,
+            types: info.type_,
+            writable: info.writable != 0,
+            owner: info.owner,
+        }
+    }
+
+    /// Returns the full name of this dataref.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the bitmask of types this dataref can be accessed as.
+    #[must_use]
+    pub fn types(&self) -> XPLMDataTypeID {
+        self.types
+    }
+
+    /// Returns true if this dataref may be written.
+    #[must_use]
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+
+    /// Returns the ID of the plugin that registered this dataref.
+    /// [`XPLM_PLUGIN_XPLANE`](xplane_sys::XPLM_PLUGIN_XPLANE) indicates X-Plane itself.
+    #[must_use]
+    pub fn owner(&self) -> XPLMPluginID {
+        self.owner
+    }
+
+    /// Gets a typed handle to this dataref.
+    /// The type is checked in the same manner as [`DataApi::find`](super::DataApi::find).
+    /// # Errors
+    /// Returns [`FindError::WrongType`] if the dataref does not have the type `T`,
+    /// or [`FindError::NotWritable`] if `A` is [`ReadWrite`] and the dataref cannot be written.
+    pub fn dataref<T: DataType + ?Sized, A: Access>(&self) -> Result<DataRef<T, A>, FindError> {
+        let dataref = DataRef::<T, ReadOnly>::from_id(self.id)?;
+        if A::writeable() && !self.writable {
+            return Err(FindError::NotWritable);
+        }
+        Ok(dataref.cast())
+    }
+}
+
+#[cfg(feature = "XPLM400")]
+#[allow(clippy::missing_fields_in_debug)]
+impl Debug for DataRefInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataRefInfo")
+            .field("name", &self.name)
+            .field("types", &self.types)
+            .field("writable", &self.writable)
+            .field("owner", &self.owner)
+            .finish()
+    }
+}
+
+/// An iterator over all datarefs registered with X-Plane.
+#[cfg(feature = "XPLM400")]
+pub struct DataRefs {
+    /// The index of the next dataref to return
+    ///
+    /// If this is equal to count, no more datarefs are available
+    pub(super) next: c_int,
+    /// The total number of datarefs available
+    pub(super) count: c_int,
+    pub(super) _phantom: NoSendSync,
+}
+
+#[cfg(feature = "XPLM400")]
+impl Iterator for DataRefs {
+    type Item = DataRefInfo;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next < self.count {
+            let mut id: XPLMDataRef = ptr::null_mut();
+            unsafe {
+                XPLMGetDataRefsByIndex(self.next, 1, &mut id);
+            }
+            self.next += 1;
+            if !id.is_null() {
+                return Some(DataRefInfo::from_id(id));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Null IDs are skipped, so fewer datarefs may be returned.
+        let remaining = usize::try_from(self.count.saturating_sub(self.next)).unwrap_or(0);
+        (0, Some(remaining))
+    }
+}
+
 /// Converts a usize into an i32. Returns `i32::MAX` if the provided size is too large for an i32
 #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
 fn array_size(size: usize) -> i32 {
@@ -264,6 +1016,32 @@ fn array_size(size: usize) -> i32 {
     }
 }
 
+/// Returns the length of an array dataref of type `sim_type`
+fn array_len(id: XPLMDataRef, sim_type: XPLMDataTypeID) -> usize {
+    if sim_type.int_array() {
+        DataRef::<[i32]> {
+            id,
+            float_fallback: false,
+            _phantom: PhantomData,
+        }
+        .len()
+    } else if sim_type.float_array() {
+        DataRef::<[f32]> {
+            id,
+            float_fallback: false,
+            _phantom: PhantomData,
+        }
+        .len()
+    } else {
+        DataRef::<[u8]> {
+            id,
+            float_fallback: false,
+            _phantom: PhantomData,
+        }
+        .len()
+    }
+}
+
 /// Errors that can occur when finding `DataRef`s
 #[derive(Snafu, Debug)]
 pub enum FindError {
@@ -286,10 +1064,283 @@ pub enum FindError {
     /// The DataRef does not have the correct type
     #[snafu(display("Incorrect DataRef type"))]
     WrongType,
+
+    /// The DataRef has fewer elements than the fixed-length array type it was found as
+    #[snafu(display("DataRef has {actual} elements, but at least {expected} are needed"))]
+    WrongLength {
+        /// The number of elements in the array type
+        expected: usize,
+        /// The number of elements in the DataRef
+        actual: usize,
+    },
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "XPLM400")]
+    use std::ptr::NonNull;
+
+    #[cfg(feature = "XPLM400")]
+    use super::*;
+    #[cfg(feature = "XPLM400")]
+    use crate::make_x;
+
+    #[test]
+    #[cfg(feature = "XPLM400")]
+    fn test_dataref_enumeration() {
+        let first_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let count_ctx = xplane_sys::XPLMCountDataRefs_context();
+        count_ctx.expect().times(2).returning_st(|| 2);
+        let by_index_ctx = xplane_sys::XPLMGetDataRefsByIndex_context();
+        by_index_ctx
+            .expect()
+            .times(2)
+            .returning_st(move |offset, count, out| {
+                assert_eq!(count, 1);
+                // The second dataref has gone bad, and should be skipped.
+                let id = if offset == 0 {
+                    first_ptr
+                } else {
+                    ptr::null_mut()
+                };
+                unsafe {
+                    *out = id;
+                }
+            });
+        let info_ctx = xplane_sys::XPLMGetDataRefInfo_context();
+        info_ctx.expect().once().return_once_st(move |id, info| {
+            assert_eq!(id, first_ptr);
+            let info = unsafe { info.as_mut().unwrap() };
+            assert_eq!(
+                info.structSize,
+                c_int::try_from(mem::size_of::<XPLMDataRefInfo_t>()).unwrap()
+            );
+            info.name = b"sim/test/dataref\0".as_ptr().cast();
+            info.type_ = XPLMDataTypeID::Float | XPLMDataTypeID::Double;
+            info.writable = 1;
+            info.owner = 0;
+        });
+        let mut x = make_x();
+        let infos: Vec<DataRefInfo> = x.data.all_datarefs().collect();
+        assert_eq!(infos.len(), 1);
+        let info = &infos[0];
+        assert_eq!(info.name(), "sim/test/dataref");
+        assert!(info.types().float());
+        assert!(info.types().double());
+        assert!(!info.types().int());
+        assert!(info.is_writable());
+        assert_eq!(info.owner(), 0);
+
+        let mut past_end = x.data.datarefs_from(5);
+        assert_eq!(past_end.size_hint(), (0, Some(0)));
+        assert!(past_end.next().is_none());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_find_types() {
+        use super::{DataRead, DataReadWrite, FindError, XPLMDataTypeID};
+        use crate::make_x;
+        use std::{cell::Cell, ffi::CStr, ptr, ptr::NonNull, rc::Rc};
+        let dataref_ptr = NonNull::<std::ffi::c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().returning_st(move |name| {
+            if unsafe { CStr::from_ptr(name) }.to_bytes() == b"sim/test/missing" {
+                ptr::null_mut()
+            } else {
+                dataref_ptr
+            }
+        });
+        let types = Rc::new(Cell::new(XPLMDataTypeID::Int));
+        let types_1 = types.clone();
+        let type_queries = Rc::new(Cell::new(0));
+        let type_queries_1 = type_queries.clone();
+        let types_ctx = xplane_sys::XPLMGetDataRefTypes_context();
+        types_ctx.expect().returning_st(move |_| {
+            type_queries_1.set(type_queries_1.get() + 1);
+            types_1.get()
+        });
+        let can_write = Rc::new(Cell::new(1));
+        let can_write_1 = can_write.clone();
+        let can_write_ctx = xplane_sys::XPLMCanWriteDataRef_context();
+        can_write_ctx
+            .expect()
+            .returning_st(move |_| can_write_1.get());
+        let float_value = Rc::new(Cell::new(2.5f32));
+        let float_value_1 = float_value.clone();
+        let getf_ctx = xplane_sys::XPLMGetDataf_context();
+        getf_ctx.expect().returning_st(move |_| float_value_1.get());
+        let float_value_2 = float_value.clone();
+        let setf_ctx = xplane_sys::XPLMSetDataf_context();
+        setf_ctx
+            .expect()
+            .returning_st(move |_, value| float_value_2.set(value));
+        let getd_ctx = xplane_sys::XPLMGetDatad_context();
+        getd_ctx.expect().returning_st(|_| 8.25);
+
+        let mut x = make_x();
+        assert!(matches!(
+            x.data.find::<i32, _>("sim/test/missing"),
+            Err(FindError::NotFound)
+        ));
+
+        // Int datarefs can be found as any integer type, or as bool
+        assert!(x.data.find::<i32, _>("sim/test/int").is_ok());
+        assert!(x.data.find::<u8, _>("sim/test/int").is_ok());
+        assert!(x.data.find::<bool, _>("sim/test/int").is_ok());
+        assert!(matches!(
+            x.data.find::<f32, _>("sim/test/int"),
+            Err(FindError::WrongType)
+        ));
+        assert!(matches!(
+            x.data.find::<f64, _>("sim/test/int"),
+            Err(FindError::WrongType)
+        ));
+
+        // Float-only datarefs can be found as f64, with conversion
+        types.set(XPLMDataTypeID::Float);
+        assert_eq!(x.data.find::<f32, _>("sim/test/float").unwrap().get(), 2.5);
+        assert_eq!(x.data.find::<f64, _>("sim/test/float").unwrap().get(), 2.5);
+        let mut writable = x.data.find_writable::<f64, _>("sim/test/float").unwrap();
+        let queries = type_queries.get();
+        writable.set(4.0);
+        assert_eq!(float_value.get(), 4.0);
+        assert_eq!(writable.get(), 4.0);
+        // The type is only checked when the dataref is found
+        assert_eq!(type_queries.get(), queries);
+        assert!(matches!(
+            x.data.find::<i32, _>("sim/test/float"),
+            Err(FindError::WrongType)
+        ));
+
+        // Doubles are read directly when the dataref has them
+        types.set(XPLMDataTypeID::Float | XPLMDataTypeID::Double);
+        assert_eq!(
+            x.data.find::<f64, _>("sim/test/double").unwrap().get(),
+            8.25
+        );
+
+        can_write.set(0);
+        assert!(matches!(
+            x.data.find_writable::<f64, _>("sim/test/double"),
+            Err(FindError::NotWritable)
+        ));
+        assert!(x.data.find::<f64, _>("sim/test/double").is_ok());
+    }
+
+    #[test]
+    fn test_find_any_data() {
+        use super::{DataValue, DataValueType, XPLMDataTypeID};
+        use crate::make_x;
+        use std::{cell::Cell, ptr, ptr::NonNull, rc::Rc};
+        let dataref_ptr = NonNull::<std::ffi::c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().returning_st(move |_| dataref_ptr);
+        let types = Rc::new(Cell::new(XPLMDataTypeID::Data));
+        let types_1 = types.clone();
+        let types_ctx = xplane_sys::XPLMGetDataRefTypes_context();
+        types_ctx.expect().returning_st(move |_| types_1.get());
+        let getb_ctx = xplane_sys::XPLMGetDatab_context();
+        getb_ctx.expect().returning_st(|_, out, offset, max| {
+            let bytes = b"abc";
+            if out.is_null() {
+                return 3;
+            }
+            let offset = usize::try_from(offset).unwrap();
+            let count = usize::try_from(max).unwrap().min(bytes.len() - offset);
+            unsafe {
+                ptr::copy_nonoverlapping(bytes[offset..].as_ptr(), out.cast::<u8>(), count);
+            }
+            i32::try_from(count).unwrap()
+        });
+
+        let mut x = make_x();
+        let string = x.data.find_any("sim/test/string").unwrap();
+        assert!(string.supports(DataValueType::Bytes));
+        assert!(!string.supports(DataValueType::IntArray));
+        assert_eq!(string.natural_type(), DataValueType::Bytes);
+        assert_eq!(string.array_len(), Some(3));
+        assert_eq!(string.get(), DataValue::Bytes(b"abc".to_vec()));
+
+        // Int arrays are not byte arrays
+        types.set(XPLMDataTypeID::IntArray);
+        let int_array = x.data.find_any("sim/test/int_array").unwrap();
+        assert!(!int_array.supports(DataValueType::Bytes));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_array_range() {
+        use super::{ArrayRead, ArrayReadWrite, DataRef, ReadWrite};
+        use std::{ffi::c_void, marker::PhantomData, ptr::NonNull};
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let set_ctx = xplane_sys::XPLMSetDatavf_context();
+        set_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id, values, offset, count| {
+                assert_eq!(id, expected_ptr);
+                assert_eq!(offset, 3);
+                assert_eq!(count, 1);
+                assert_eq!(unsafe { *values }, 0.5);
+            });
+        let get_ctx = xplane_sys::XPLMGetDatavf_context();
+        get_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id, values, offset, max| {
+                assert_eq!(id, expected_ptr);
+                assert_eq!(offset, 7);
+                assert_eq!(max, 1);
+                unsafe {
+                    *values = 1.5;
+                }
+                1
+            });
+        let mut dataref = DataRef::<[f32], ReadWrite> {
+            id: expected_ptr,
+            float_fallback: false,
+            _phantom: PhantomData,
+        };
+        dataref.set_at(3, 0.5);
+        assert_eq!(dataref.get_at(7), Some(1.5));
+    }
+
+    #[test]
+    fn test_data_value_convert() {
+        use super::{DataValue, DataValueError, DataValueType};
+        assert_eq!(
+            DataValue::Int(3).convert(DataValueType::Double).unwrap(),
+            DataValue::Double(3.0)
+        );
+        assert_eq!(
+            DataValue::Float(-2.0).convert(DataValueType::Int).unwrap(),
+            DataValue::Int(-2)
+        );
+        assert!(matches!(
+            DataValue::Float(2.5).convert(DataValueType::Int),
+            Err(DataValueError::OutOfRange)
+        ));
+        assert!(matches!(
+            DataValue::Int(16_777_217).convert(DataValueType::Float),
+            Err(DataValueError::OutOfRange)
+        ));
+        assert!(matches!(
+            DataValue::Double(1e300).convert(DataValueType::Float),
+            Err(DataValueError::OutOfRange)
+        ));
+        assert_eq!(
+            DataValue::IntArray(vec![1, 2])
+                .convert(DataValueType::FloatArray)
+                .unwrap(),
+            DataValue::FloatArray(vec![1.0, 2.0])
+        );
+        assert!(matches!(
+            DataValue::Bytes(vec![1]).convert(DataValueType::IntArray),
+            Err(DataValueError::Incompatible { .. })
+        ));
+    }
+
     /// Checks that the as operator truncates values
     #[test]
     #[allow(clippy::cast_possible_truncation)]
@@ -0,0 +1,183 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt;
+
+use snafu::prelude::*;
+
+use super::{
+    borrowed::{DataRef, FindError},
+    DataApi, DataType, ReadOnly, ReadWrite,
+};
+
+/// A set of datarefs that can be found together
+///
+/// With the `derive` feature, this can be derived with [`DataRefs`](super::DataRefs).
+pub trait DataRefBundle: Sized {
+    /// Finds every dataref in this set.
+    /// # Errors
+    /// Returns an error listing every dataref that could not be found.
+    fn find_all(data: &mut DataApi) -> Result<Self, FindAllError>;
+}
+
+/// The failures from finding a [`DataRefBundle`]
+#[derive(Snafu, Debug)]
+#[snafu(display("{} DataRef(s) could not be found: {}", failures.len(), Failures(failures)))]
+pub struct FindAllError {
+    /// The name of each dataref that could not be found, and why
+    failures: Vec<(String, FindError)>,
+}
+
+impl FindAllError {
+    /// Returns the name of each dataref that could not be found, and the reason.
+    #[must_use]
+    pub fn failures(&self) -> &[(String, FindError)] {
+        &self.failures
+    }
+
+    /// Consumes this error, returning the name of each dataref that could not be found,
+    /// and the reason.
+    #[must_use]
+    pub fn into_failures(self) -> Vec<(String, FindError)> {
+        self.failures
+    }
+
+    #[doc(hidden)]
+    #[must_use]
+    pub fn __new() -> Self {
+        FindAllError {
+            failures: Vec::new(),
+        }
+    }
+
+    /// Finds a readable dataref, recording the failure if it can't be found.
+    #[doc(hidden)]
+    pub fn __find<T: DataType + ?Sized>(
+        &mut self,
+        data: &mut DataApi,
+        name: &str,
+    ) -> Option<DataRef<T, ReadOnly>> {
+        self.record(name, data.find(name))
+    }
+
+    /// Finds a writable dataref, recording the failure if it can't be found.
+    #[doc(hidden)]
+    pub fn __find_writable<T: DataType + ?Sized>(
+        &mut self,
+        data: &mut DataApi,
+        name: &str,
+    ) -> Option<DataRef<T, ReadWrite>> {
+        self.record(name, data.find_writable(name))
+    }
+
+    fn record<D>(&mut self, name: &str, result: Result<D, FindError>) -> Option<D> {
+        match result {
+            Ok(dataref) => Some(dataref),
+            Err(e) => {
+                self.failures.push((name.to_owned(), e));
+                None
+            }
+        }
+    }
+}
+
+/// Formats a list of failures as `name (reason), name (reason)`
+struct Failures<'a>(&'a [(String, FindError)]);
+
+impl fmt::Display for Failures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, e)) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{name} ({e})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+    use crate::{data::DataRefs, make_x};
+
+    #[derive(DataRefs, Debug)]
+    #[allow(dead_code)]
+    struct TestBundle {
+        #[dataref("xplane_rs/test/first")]
+        first: DataRef<f32>,
+        #[dataref("xplane_rs/test/second", writable)]
+        second: DataRef<i32, ReadWrite>,
+        #[dataref("xplane_rs/test/third")]
+        #[dataref(writable)]
+        third: DataRef<f64, ReadWrite>,
+    }
+
+    #[test]
+    fn test_find_all_reports_every_failure() {
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().times(3).returning_st(|name| {
+            let name = unsafe { CStr::from_ptr(name) };
+            assert!(name.to_str().unwrap().starts_with("xplane_rs/test/"));
+            std::ptr::null_mut()
+        });
+
+        let mut x = make_x();
+        let error = x.data.find_all::<TestBundle>().unwrap_err();
+        let names: Vec<&str> = error
+            .failures()
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "xplane_rs/test/first",
+                "xplane_rs/test/second",
+                "xplane_rs/test/third"
+            ]
+        );
+        assert!(error
+            .failures()
+            .iter()
+            .all(|(_, e)| matches!(e, FindError::NotFound)));
+    }
+
+    #[test]
+    fn test_find_all_checks_writability() {
+        use xplane_sys::XPLMDataTypeID;
+
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().times(3).returning_st(|name| {
+            let name = unsafe { CStr::from_ptr(name) };
+            let index = match name.to_bytes() {
+                b"xplane_rs/test/first" => 1,
+                b"xplane_rs/test/second" => 2,
+                _ => 3,
+            };
+            std::ptr::null_mut::<u8>().wrapping_add(index).cast()
+        });
+        let types_ctx = xplane_sys::XPLMGetDataRefTypes_context();
+        types_ctx.expect().returning_st(|id| match id as usize {
+            1 => XPLMDataTypeID::Float,
+            2 => XPLMDataTypeID::Int,
+            _ => XPLMDataTypeID::Double,
+        });
+        // Only the second dataref can be written
+        let can_write_ctx = xplane_sys::XPLMCanWriteDataRef_context();
+        can_write_ctx
+            .expect()
+            .times(2)
+            .returning_st(|id| i32::from(id as usize == 2));
+
+        let mut x = make_x();
+        let error = x.data.find_all::<TestBundle>().unwrap_err();
+        let failures = error.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "xplane_rs/test/third");
+        assert!(matches!(failures[0].1, FindError::NotWritable));
+    }
+}
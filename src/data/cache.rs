@@ -0,0 +1,471 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    marker::PhantomData,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    flight_loop::{FlightLoop, FlightLoopPhase, LoopResult, LoopState},
+    XPAPI,
+};
+
+use super::{Access, DataRead, DataReadWrite, ReadOnly, ReadWrite};
+
+/// Keeps a snapshot of a group of datarefs, refreshed once per flight loop cycle
+///
+/// Each dataref added to the cache returns a [`CachedData`] handle, which can be cloned and
+/// passed to every module that needs the value. Reads through a handle return the value from
+/// the latest snapshot instead of calling into X-Plane. Writes through a handle are visible to
+/// later reads immediately, and are sent to X-Plane in one batch at the end of the cycle. If a
+/// value is written several times in one cycle, only the last write is sent.
+///
+/// The cache is refreshed from an internal flight loop in the phase chosen when it was
+/// created, and batched writes are flushed from a second flight loop in the other phase. For
+/// example, a cache refreshed before the flight model flushes its writes after the flight
+/// model. Writes still waiting when the snapshot is refreshed are flushed first, so they are
+/// never lost. Dropping the cache stops refreshing and flushing. Handles that outlive it keep
+/// their last values.
+pub struct DataCache {
+    /// The entries and statistics, shared with the flight loop callbacks
+    inner: Rc<RefCell<CacheInner>>,
+    /// The flight loop that refreshes the snapshot
+    _refresh_loop: FlightLoop<()>,
+    /// The flight loop that flushes batched writes
+    _flush_loop: FlightLoop<()>,
+}
+
+/// Counts of reads and writes made through a [`DataCache`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads made through [`CachedData`] handles
+    pub reads: u64,
+    /// Writes made through [`CachedData`] handles
+    pub writes: u64,
+    /// Values read from X-Plane, when refreshing the snapshot or reading an invalidated value
+    pub ffi_reads: u64,
+    /// Values written to X-Plane when flushing batched writes
+    pub ffi_writes: u64,
+}
+
+impl CacheStats {
+    /// Returns the number of calls into X-Plane that the cache avoided, compared to reading
+    /// and writing every dataref directly.
+    ///
+    /// This is negative if the cache made more calls than direct access would have, for example
+    /// because it refreshes datarefs that are rarely read.
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn ffi_calls_saved(&self) -> i64 {
+        let direct = self.reads.saturating_add(self.writes);
+        let actual = self.ffi_reads.saturating_add(self.ffi_writes);
+        direct as i64 - actual as i64
+    }
+}
+
+struct CacheInner {
+    entries: Vec<Weak<dyn Entry>>,
+    stats: Rc<Cell<CacheStats>>,
+}
+
+impl CacheInner {
+    /// Flushes batched writes, then refreshes every entry that still has a handle
+    fn refresh(&mut self) {
+        self.entries.retain(|entry| {
+            let Some(entry) = entry.upgrade() else {
+                return false;
+            };
+            entry.flush(&self.stats);
+            entry.refresh(&self.stats);
+            true
+        });
+    }
+
+    /// Flushes batched writes
+    fn flush(&self) {
+        for entry in self.entries.iter().filter_map(Weak::upgrade) {
+            entry.flush(&self.stats);
+        }
+    }
+}
+
+impl DataCache {
+    pub(super) fn new(phase: FlightLoopPhase) -> Self {
+        let inner = Rc::new(RefCell::new(CacheInner {
+            entries: Vec::new(),
+            stats: Rc::new(Cell::new(CacheStats::default())),
+        }));
+        let refresh_inner = Rc::clone(&inner);
+        let refresh = move |_x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+            refresh_inner.borrow_mut().refresh();
+            LoopResult::NextLoop
+        };
+        let mut refresh_loop = FlightLoop::new(phase, refresh, ());
+        refresh_loop.schedule_immediate();
+        let flush_inner = Rc::clone(&inner);
+        let flush = move |_x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+            flush_inner.borrow().flush();
+            LoopResult::NextLoop
+        };
+        let flush_phase = if phase == FlightLoopPhase::BeforeFlightModel {
+            FlightLoopPhase::AfterFlightModel
+        } else {
+            FlightLoopPhase::BeforeFlightModel
+        };
+        let mut flush_loop = FlightLoop::new(flush_phase, flush, ());
+        flush_loop.schedule_immediate();
+        DataCache {
+            inner,
+            _refresh_loop: refresh_loop,
+            _flush_loop: flush_loop,
+        }
+    }
+
+    /// Adds a dataref to the cache, and returns a read-only handle to its cached value.
+    ///
+    /// The value is read immediately, so the handle is usable before the first refresh.
+    pub fn add<T, D>(&mut self, dataref: D) -> CachedData<T, ReadOnly>
+    where
+        T: Clone + 'static,
+        D: DataRead<T> + 'static,
+    {
+        self.insert(Box::new(ReadSource(dataref)))
+    }
+
+    /// Adds a writable dataref to the cache, and returns a writable handle to its cached value.
+    ///
+    /// The value is read immediately, so the handle is usable before the first refresh.
+    pub fn add_writable<T, D>(&mut self, dataref: D) -> CachedData<T, ReadWrite>
+    where
+        T: Clone + 'static,
+        D: DataReadWrite<T> + 'static,
+    {
+        self.insert(Box::new(ReadWriteSource(dataref)))
+    }
+
+    fn insert<T: Clone + 'static, A>(&mut self, source: Box<dyn Source<T>>) -> CachedData<T, A> {
+        let mut inner = self.inner.borrow_mut();
+        let slot = Rc::new(RefCell::new(Slot {
+            source,
+            value: None,
+            pending: None,
+        }));
+        slot.refresh(&inner.stats);
+        let entry: Rc<dyn Entry> = slot.clone();
+        inner.entries.push(Rc::downgrade(&entry));
+        CachedData {
+            slot,
+            stats: Rc::clone(&inner.stats),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Marks every cached value as out of date.
+    ///
+    /// The next read of each value comes from X-Plane, and is cached until the next refresh.
+    /// Batched writes are not affected.
+    pub fn invalidate(&mut self) {
+        for entry in self.live_entries() {
+            entry.invalidate();
+        }
+    }
+
+    /// Sends batched writes to X-Plane now, instead of at the end of the cycle.
+    pub fn flush(&mut self) {
+        self.inner.borrow().flush();
+    }
+
+    /// Returns the read and write counts since the cache was created or the counts were reset.
+    #[must_use]
+    pub fn stats(&self) -> CacheStats {
+        self.inner.borrow().stats.get()
+    }
+
+    /// Resets the read and write counts to zero.
+    pub fn reset_stats(&mut self) {
+        self.inner.borrow().stats.set(CacheStats::default());
+    }
+
+    /// Returns the number of datarefs in the cache that still have a handle
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.live_entries().len()
+    }
+
+    /// Returns true if no datarefs in the cache still have a handle
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn live_entries(&self) -> Vec<Rc<dyn Entry>> {
+        self.inner
+            .borrow()
+            .entries
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for DataCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataCache")
+            .field("len", &self.len())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// A handle to a value in a [`DataCache`]
+///
+/// Handles can be cloned. All clones share the same cached value.
+///
+/// A is the access level (`ReadOnly` or `ReadWrite`)
+pub struct CachedData<T, A = ReadOnly> {
+    slot: Rc<RefCell<Slot<T>>>,
+    stats: Rc<Cell<CacheStats>>,
+    _phantom: PhantomData<A>,
+}
+
+impl<T, A> CachedData<T, A> {
+    /// Marks this value as out of date, so that the next read comes from X-Plane.
+    pub fn invalidate(&self) {
+        self.slot.borrow_mut().value = None;
+    }
+}
+
+impl<T: Clone, A: Access> DataRead<T> for CachedData<T, A> {
+    fn get(&self) -> T {
+        update_stats(&self.stats, |stats| stats.reads += 1);
+        let mut slot = self.slot.borrow_mut();
+        if let Some(value) = &slot.value {
+            return value.clone();
+        }
+        update_stats(&self.stats, |stats| stats.ffi_reads += 1);
+        let value = slot.source.read();
+        slot.value = Some(value.clone());
+        value
+    }
+}
+
+impl<T: Clone> DataReadWrite<T> for CachedData<T, ReadWrite> {
+    /// Caches a value, and queues it to be written at the end of the cycle
+    fn set(&mut self, value: T) {
+        update_stats(&self.stats, |stats| stats.writes += 1);
+        let mut slot = self.slot.borrow_mut();
+        slot.value = Some(value.clone());
+        slot.pending = Some(value);
+    }
+}
+
+impl<T, A> Clone for CachedData<T, A> {
+    fn clone(&self) -> Self {
+        CachedData {
+            slot: Rc::clone(&self.slot),
+            stats: Rc::clone(&self.stats),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<T: fmt::Debug, A> fmt::Debug for CachedData<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slot = self.slot.borrow();
+        f.debug_struct("CachedData")
+            .field("value", &slot.value)
+            .field("pending", &slot.pending)
+            .finish()
+    }
+}
+
+/// A cached value and the dataref it comes from
+struct Slot<T> {
+    source: Box<dyn Source<T>>,
+    /// The cached value, or [`None`] if it has been invalidated
+    value: Option<T>,
+    /// A value waiting to be written
+    pending: Option<T>,
+}
+
+/// Type-erased access to a [`Slot`] for the cache
+trait Entry {
+    fn refresh(&self, stats: &Cell<CacheStats>);
+    fn flush(&self, stats: &Cell<CacheStats>);
+    fn invalidate(&self);
+}
+
+impl<T> Entry for RefCell<Slot<T>> {
+    fn refresh(&self, stats: &Cell<CacheStats>) {
+        let mut slot = self.borrow_mut();
+        slot.value = Some(slot.source.read());
+        update_stats(stats, |stats| stats.ffi_reads += 1);
+    }
+    fn flush(&self, stats: &Cell<CacheStats>) {
+        let mut slot = self.borrow_mut();
+        if let Some(value) = slot.pending.take() {
+            slot.source.write(value);
+            update_stats(stats, |stats| stats.ffi_writes += 1);
+        }
+    }
+    fn invalidate(&self) {
+        self.borrow_mut().value = None;
+    }
+}
+
+/// A dataref that a [`Slot`] reads from and writes to
+trait Source<T> {
+    fn read(&self) -> T;
+    fn write(&mut self, value: T);
+}
+
+/// A source that can only be read
+struct ReadSource<D>(D);
+
+impl<T, D: DataRead<T>> Source<T> for ReadSource<D> {
+    fn read(&self) -> T {
+        self.0.get()
+    }
+    fn write(&mut self, _value: T) {
+        // Read-only handles cannot queue writes.
+    }
+}
+
+/// A source that can be read and written
+struct ReadWriteSource<D>(D);
+
+impl<T, D: DataReadWrite<T>> Source<T> for ReadWriteSource<D> {
+    fn read(&self) -> T {
+        self.0.get()
+    }
+    fn write(&mut self, value: T) {
+        self.0.set(value);
+    }
+}
+
+fn update_stats(stats: &Cell<CacheStats>, update: impl FnOnce(&mut CacheStats)) {
+    let mut value = stats.get();
+    update(&mut value);
+    stats.set(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{c_float, c_int, c_void},
+        ptr::NonNull,
+    };
+
+    use super::*;
+    use crate::make_x;
+
+    struct FakeData {
+        value: Rc<Cell<i32>>,
+        ffi_writes: Rc<Cell<u32>>,
+    }
+
+    impl DataRead<i32> for FakeData {
+        fn get(&self) -> i32 {
+            self.value.get()
+        }
+    }
+
+    impl DataReadWrite<i32> for FakeData {
+        fn set(&mut self, value: i32) {
+            self.ffi_writes.set(self.ffi_writes.get() + 1);
+            self.value.set(value);
+        }
+    }
+
+    type Callback = unsafe extern "C-unwind" fn(c_float, c_float, c_int, *mut c_void) -> c_float;
+    /// The phase, callback and refcon of each flight loop created
+    type CreatedLoops = Rc<RefCell<Vec<(FlightLoopPhase, Callback, *mut c_void)>>>;
+
+    #[test]
+    fn test_cache() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let loops: CreatedLoops = Rc::new(RefCell::new(Vec::new()));
+        let loops_1 = loops.clone();
+        let create_ctx = xplane_sys::XPLMCreateFlightLoop_context();
+        create_ctx.expect().times(2).returning_st(move |s| {
+            let s = unsafe { *s };
+            loops_1
+                .borrow_mut()
+                .push((s.phase, s.callbackFunc.unwrap(), s.refcon));
+            expected_ptr
+        });
+        let schedule_ctx = xplane_sys::XPLMScheduleFlightLoop_context();
+        schedule_ctx.expect().times(2).returning_st(|_, _, _| ());
+        let destroy_ctx = xplane_sys::XPLMDestroyFlightLoop_context();
+        destroy_ctx.expect().times(2).returning_st(|_| ());
+
+        let value = Rc::new(Cell::new(5));
+        let ffi_writes = Rc::new(Cell::new(0));
+        let mut x = make_x();
+        let mut cache = x.data.new_cache(FlightLoopPhase::BeforeFlightModel);
+        let mut handle = cache.add_writable(FakeData {
+            value: value.clone(),
+            ffi_writes: ffi_writes.clone(),
+        });
+        let reader = handle.clone();
+        assert_eq!(cache.len(), 1);
+
+        // Reads come from the snapshot
+        value.set(6);
+        assert_eq!(reader.get(), 5);
+        assert_eq!(handle.get(), 5);
+
+        // Writes are visible immediately, and only the last one is sent
+        handle.set(7);
+        handle.set(8);
+        assert_eq!(reader.get(), 8);
+        assert_eq!(value.get(), 6);
+        let loops = loops.borrow();
+        let [(refresh_phase, refresh, refresh_refcon), (flush_phase, flush, flush_refcon)] =
+            loops[..]
+        else {
+            panic!("Expected two flight loops");
+        };
+        assert_eq!(refresh_phase, FlightLoopPhase::BeforeFlightModel);
+        // Writes are sent at the end of the cycle, after the flight model
+        assert_eq!(flush_phase, FlightLoopPhase::AfterFlightModel);
+        unsafe {
+            flush(0.1, 0.1, 1, flush_refcon);
+        }
+        assert_eq!(value.get(), 8);
+        assert_eq!(ffi_writes.get(), 1);
+        unsafe {
+            refresh(0.1, 0.1, 1, refresh_refcon);
+        }
+        assert_eq!(ffi_writes.get(), 1);
+
+        // Invalidated values are read again
+        value.set(9);
+        assert_eq!(reader.get(), 8);
+        cache.invalidate();
+        assert_eq!(reader.get(), 9);
+        assert_eq!(reader.get(), 9);
+
+        let stats = cache.stats();
+        assert_eq!(
+            stats,
+            CacheStats {
+                reads: 6,
+                writes: 2,
+                ffi_reads: 3,
+                ffi_writes: 1,
+            }
+        );
+        assert_eq!(stats.ffi_calls_saved(), 4);
+
+        drop(handle);
+        drop(reader);
+        assert!(cache.is_empty());
+    }
+}
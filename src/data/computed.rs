@@ -0,0 +1,464 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::ffi::{c_int, c_void};
+use std::{cmp, ffi::CString, fmt, marker::PhantomData, slice};
+
+use xplane_sys::{
+    XPLMDataRef, XPLMFindDataRef, XPLMGetDatab_f, XPLMGetDatad_f, XPLMGetDataf_f, XPLMGetDatai_f,
+    XPLMGetDatavf_f, XPLMGetDatavi_f, XPLMRegisterDataAccessor, XPLMSetDatab_f, XPLMSetDatad_f,
+    XPLMSetDataf_f, XPLMSetDatai_f, XPLMSetDatavf_f, XPLMSetDatavi_f, XPLMUnregisterDataAccessor,
+};
+
+use crate::{make_x, NoSendSync, XPAPI};
+
+use super::{
+    editor, has_data_type, owned::CreateError, Access, ArrayType, DataType, ReadOnly, ScalarType,
+};
+
+/// A dataref owned by this plugin, whose value is computed by a handler whenever it is read
+///
+/// The access parameter of this type determines whether X-Plane and other plugins can write
+/// this dataref. Writes are passed to the handler.
+///
+/// The dataref is unregistered when this is dropped.
+pub struct ComputedData<T: ?Sized, A = ReadOnly> {
+    /// The dataref handle
+    id: XPLMDataRef,
+    /// The handler, allocated in a Box and provided as a refcon to the callbacks
+    handler: *mut c_void,
+    /// Frees the handler
+    drop_handler: unsafe fn(*mut c_void),
+    /// Data access and type phantom data
+    _phantom: PhantomData<(NoSendSync, A, *const T)>,
+}
+
+impl<T: ScalarType, A: Access> ComputedData<T, A> {
+    pub(super) fn new<S: AsRef<str>, H: ComputedHandler<T>>(
+        name: S,
+        handler: H,
+    ) -> Result<Self, CreateError> {
+        let writeable = A::writeable();
+        let sim_type = T::sim_type();
+        let int_read: XPLMGetDatai_f = Some(read_int::<T, H>);
+        let int_write: XPLMSetDatai_f = Some(write_int::<T, H>);
+        let float_read: XPLMGetDataf_f = Some(read_float::<T, H>);
+        let float_write: XPLMSetDataf_f = Some(write_float::<T, H>);
+        let double_read: XPLMGetDatad_f = Some(read_double::<T, H>);
+        let double_write: XPLMSetDatad_f = Some(write_double::<T, H>);
+        let accessors = Accessors {
+            int_read: int_read.filter(|_| sim_type.int()),
+            int_write: int_write.filter(|_| sim_type.int() && writeable),
+            float_read: float_read.filter(|_| sim_type.float()),
+            float_write: float_write.filter(|_| sim_type.float() && writeable),
+            double_read: double_read.filter(|_| sim_type.double()),
+            double_write: double_write.filter(|_| sim_type.double() && writeable),
+            ..Accessors::default()
+        };
+        Self::register(name.as_ref(), handler, &accessors)
+    }
+}
+
+impl<T: ArrayType + ?Sized + 'static, A: Access> ComputedData<T, A> {
+    pub(super) fn new_array<S: AsRef<str>, H: ComputedArrayHandler<T>>(
+        name: S,
+        handler: H,
+    ) -> Result<Self, CreateError> {
+        let writeable = A::writeable();
+        let sim_type = T::sim_type();
+        let int_array_read: XPLMGetDatavi_f = Some(array_read::<T, H, c_int>);
+        let int_array_write: XPLMSetDatavi_f = Some(array_write::<T, H, c_int>);
+        let float_array_read: XPLMGetDatavf_f = Some(array_read::<T, H, f32>);
+        let float_array_write: XPLMSetDatavf_f = Some(array_write::<T, H, f32>);
+        let byte_array_read: XPLMGetDatab_f = Some(array_read::<T, H, c_void>);
+        let byte_array_write: XPLMSetDatab_f = Some(array_write::<T, H, c_void>);
+        // The element type of T matches the element type of the sim type, so each
+        // callback can cast the sim's pointer to a pointer to T::Element.
+        let accessors = Accessors {
+            int_array_read: int_array_read.filter(|_| sim_type.int_array()),
+            int_array_write: int_array_write.filter(|_| sim_type.int_array() && writeable),
+            float_array_read: float_array_read.filter(|_| sim_type.float_array()),
+            float_array_write: float_array_write.filter(|_| sim_type.float_array() && writeable),
+            byte_array_read: byte_array_read.filter(|_| has_data_type(sim_type)),
+            byte_array_write: byte_array_write.filter(|_| has_data_type(sim_type) && writeable),
+            ..Accessors::default()
+        };
+        Self::register(name.as_ref(), handler, &accessors)
+    }
+}
+
+impl<T: DataType + ?Sized, A: Access> ComputedData<T, A> {
+    /// Registers the dataref with the provided accessors, using a boxed `handler` as the refcon.
+    fn register<H: 'static>(
+        name: &str,
+        handler: H,
+        accessors: &Accessors,
+    ) -> Result<Self, CreateError> {
+        let name_c = CString::new(name)?;
+
+        let existing = unsafe { XPLMFindDataRef(name_c.as_ptr()) };
+        if !existing.is_null() {
+            return Err(CreateError::Exists);
+        }
+
+        let handler = Box::into_raw(Box::new(handler)).cast::<c_void>();
+
+        let id = unsafe {
+            XPLMRegisterDataAccessor(
+                name_c.as_ptr(),
+                T::sim_type(),
+                i32::from(A::writeable()),
+                accessors.int_read,
+                accessors.int_write,
+                accessors.float_read,
+                accessors.float_write,
+                accessors.double_read,
+                accessors.double_write,
+                accessors.int_array_read,
+                accessors.int_array_write,
+                accessors.float_array_read,
+                accessors.float_array_write,
+                accessors.byte_array_read,
+                accessors.byte_array_write,
+                handler,
+                handler,
+            )
+        };
+
+        assert!(!id.is_null(), "Dataref ID of created dataref is null!");
+        editor::announce(&name_c);
+        Ok(ComputedData {
+            id,
+            handler,
+            drop_handler: drop_boxed::<H>,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized, A> Drop for ComputedData<T, A> {
+    fn drop(&mut self) {
+        unsafe {
+            XPLMUnregisterDataAccessor(self.id);
+            (self.drop_handler)(self.handler);
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<T: ?Sized, A> fmt::Debug for ComputedData<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComputedData")
+            .field("id", &"[dataref handle]")
+            .field("handler", &"[handler]")
+            .finish()
+    }
+}
+
+/// The set of accessor callbacks passed to `XPLMRegisterDataAccessor`
+#[derive(Default)]
+struct Accessors {
+    int_read: XPLMGetDatai_f,
+    int_write: XPLMSetDatai_f,
+    float_read: XPLMGetDataf_f,
+    float_write: XPLMSetDataf_f,
+    double_read: XPLMGetDatad_f,
+    double_write: XPLMSetDatad_f,
+    int_array_read: XPLMGetDatavi_f,
+    int_array_write: XPLMSetDatavi_f,
+    float_array_read: XPLMGetDatavf_f,
+    float_array_write: XPLMSetDatavf_f,
+    byte_array_read: XPLMGetDatab_f,
+    byte_array_write: XPLMSetDatab_f,
+}
+
+/// Trait for things that can compute the value of a single-value dataref
+pub trait ComputedHandler<T>: 'static {
+    /// Called when X-Plane or another plugin reads the dataref. Returns the current value.
+    fn read(&mut self, x: &mut XPAPI) -> T;
+    /// Called when X-Plane or another plugin writes the dataref.
+    ///
+    /// This is only called if the dataref was created as [`ReadWrite`](super::ReadWrite).
+    /// The default implementation ignores the value.
+    fn write(&mut self, x: &mut XPAPI, value: T) {
+        let _ = (x, value);
+    }
+}
+
+/// Closures can be used as read-only [`ComputedHandler`]s
+impl<T, F> ComputedHandler<T> for F
+where
+    F: FnMut(&mut XPAPI) -> T + 'static,
+{
+    fn read(&mut self, x: &mut XPAPI) -> T {
+        self(x)
+    }
+}
+
+/// A pair of closures can be used as a read-write [`ComputedHandler`].
+/// The first closure reads the value, and the second writes it.
+impl<T, R, W> ComputedHandler<T> for (R, W)
+where
+    R: FnMut(&mut XPAPI) -> T + 'static,
+    W: FnMut(&mut XPAPI, T) + 'static,
+{
+    fn read(&mut self, x: &mut XPAPI) -> T {
+        (self.0)(x)
+    }
+    fn write(&mut self, x: &mut XPAPI, value: T) {
+        (self.1)(x, value);
+    }
+}
+
+/// Trait for things that can compute the contents of an array dataref
+pub trait ComputedArrayHandler<T: ArrayType + ?Sized>: 'static {
+    /// Returns the current length of the array.
+    fn len(&mut self, x: &mut XPAPI) -> usize;
+    /// Called when X-Plane or another plugin reads the dataref.
+    ///
+    /// Copies values starting at index `offset` into `dest`, and returns the number of
+    /// values copied.
+    fn read(&mut self, x: &mut XPAPI, offset: usize, dest: &mut [T::Element]) -> usize;
+    /// Called when X-Plane or another plugin writes the dataref, starting at index `offset`.
+    ///
+    /// This is only called if the dataref was created as [`ReadWrite`](super::ReadWrite).
+    /// The default implementation ignores the values.
+    fn write(&mut self, x: &mut XPAPI, offset: usize, values: &[T::Element]) {
+        let _ = (x, offset, values);
+    }
+}
+
+/// Closures returning the whole array can be used as read-only [`ComputedArrayHandler`]s.
+///
+/// The closure is called once for every length query and every read.
+impl<T, F> ComputedArrayHandler<T> for F
+where
+    T: ArrayType + ?Sized,
+    T::Element: Copy,
+    F: FnMut(&mut XPAPI) -> Vec<T::Element> + 'static,
+{
+    fn len(&mut self, x: &mut XPAPI) -> usize {
+        self(x).len()
+    }
+    fn read(&mut self, x: &mut XPAPI, offset: usize, dest: &mut [T::Element]) -> usize {
+        let values = self(x);
+        let values = values.get(offset..).unwrap_or_default();
+        let copy_length = cmp::min(dest.len(), values.len());
+        dest[..copy_length].copy_from_slice(&values[..copy_length]);
+        copy_length
+    }
+}
+
+// Read/write callbacks
+// The refcon is a pointer to the handler
+
+/// Frees a handler allocated with [`Box`].
+unsafe fn drop_boxed<H>(handler: *mut c_void) {
+    let _ = unsafe { Box::from_raw(handler.cast::<H>()) };
+}
+
+/// Gets the handler from a refcon.
+unsafe fn handler<'a, H>(refcon: *mut c_void) -> &'a mut H {
+    unsafe { refcon.cast::<H>().as_mut().unwrap() } // UNWRAP: This will not be a null pointer.
+}
+
+/// Int read callback
+unsafe extern "C-unwind" fn read_int<T: ScalarType, H: ComputedHandler<T>>(
+    refcon: *mut c_void,
+) -> c_int {
+    let mut x = make_x();
+    unsafe { handler::<H>(refcon) }.read(&mut x).to_int()
+}
+
+/// Int write callback
+unsafe extern "C-unwind" fn write_int<T: ScalarType, H: ComputedHandler<T>>(
+    refcon: *mut c_void,
+    value: c_int,
+) {
+    let mut x = make_x();
+    unsafe { handler::<H>(refcon) }.write(&mut x, T::from_int(value));
+}
+
+/// Float read callback
+unsafe extern "C-unwind" fn read_float<T: ScalarType, H: ComputedHandler<T>>(
+    refcon: *mut c_void,
+) -> f32 {
+    let mut x = make_x();
+    unsafe { handler::<H>(refcon) }.read(&mut x).to_float()
+}
+
+/// Float write callback
+unsafe extern "C-unwind" fn write_float<T: ScalarType, H: ComputedHandler<T>>(
+    refcon: *mut c_void,
+    value: f32,
+) {
+    let mut x = make_x();
+    unsafe { handler::<H>(refcon) }.write(&mut x, T::from_float(value));
+}
+
+/// Double read callback
+unsafe extern "C-unwind" fn read_double<T: ScalarType, H: ComputedHandler<T>>(
+    refcon: *mut c_void,
+) -> f64 {
+    let mut x = make_x();
+    unsafe { handler::<H>(refcon) }.read(&mut x).to_double()
+}
+
+/// Double write callback
+unsafe extern "C-unwind" fn write_double<T: ScalarType, H: ComputedHandler<T>>(
+    refcon: *mut c_void,
+    value: f64,
+) {
+    let mut x = make_x();
+    unsafe { handler::<H>(refcon) }.write(&mut x, T::from_double(value));
+}
+
+/// If values is null, returns the length of the array.
+/// Otherwise, asks the handler for up to max elements starting at offset offset and copies them
+/// into values.
+///
+/// `N` is the element type X-Plane uses, which must have the same layout as `T::Element`.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+unsafe extern "C-unwind" fn array_read<T, H, N>(
+    refcon: *mut c_void,
+    values: *mut N,
+    offset: c_int,
+    max: c_int,
+) -> c_int
+where
+    T: ArrayType + ?Sized,
+    H: ComputedArrayHandler<T>,
+{
+    let handler = unsafe { handler::<H>(refcon) };
+    let mut x = make_x();
+    if values.is_null() {
+        handler.len(&mut x) as c_int
+    } else {
+        let (Ok(offset), Ok(max)) = (usize::try_from(offset), usize::try_from(max)) else {
+            return 0;
+        };
+        let dest = unsafe { slice::from_raw_parts_mut(values.cast::<T::Element>(), max) };
+        handler.read(&mut x, offset, dest) as c_int
+    }
+}
+
+/// Passes up to max items from values to the handler, starting at offset offset
+///
+/// `N` is the element type X-Plane uses, which must have the same layout as `T::Element`.
+unsafe extern "C-unwind" fn array_write<T, H, N>(
+    refcon: *mut c_void,
+    values: *mut N,
+    offset: c_int,
+    max: c_int,
+) where
+    T: ArrayType + ?Sized,
+    H: ComputedArrayHandler<T>,
+{
+    if values.is_null() {
+        return;
+    }
+    let (Ok(offset), Ok(max)) = (usize::try_from(offset), usize::try_from(max)) else {
+        return;
+    };
+    let handler = unsafe { handler::<H>(refcon) };
+    let mut x = make_x();
+    let values = unsafe { slice::from_raw_parts(values.cast::<T::Element>(), max) };
+    handler.write(&mut x, offset, values);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, ptr, ptr::NonNull, rc::Rc};
+
+    use super::*;
+    use xplane_sys::XPLMDataTypeID;
+
+    use crate::data::ReadWrite;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_computed_scalar() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx
+            .expect()
+            .once()
+            .return_once_st(|_| std::ptr::null_mut());
+        let callbacks = Rc::new(RefCell::new(None));
+        let callbacks_1 = callbacks.clone();
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx.expect().once().return_once_st(
+            move |_,
+                  type_,
+                  writable,
+                  ir,
+                  iw,
+                  fr,
+                  fw,
+                  dr,
+                  dw,
+                  _,
+                  _,
+                  _,
+                  _,
+                  _,
+                  _,
+                  read_refcon,
+                  write_refcon| {
+                assert_eq!(type_, XPLMDataTypeID::Float);
+                assert_eq!(writable, 1);
+                assert!(ir.is_none() && iw.is_none() && dr.is_none() && dw.is_none());
+                assert_eq!(read_refcon, write_refcon);
+                *callbacks_1.borrow_mut() = Some((fr.unwrap(), fw.unwrap(), read_refcon));
+                expected_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, expected_ptr));
+
+        let stored = Rc::new(RefCell::new(1.5f32));
+        let stored_read = stored.clone();
+        let stored_write = stored.clone();
+        let mut x = make_x();
+        let computed: ComputedData<f32, ReadWrite> = x
+            .data
+            .new_computed(
+                "xplane_rs/test/computed",
+                (
+                    move |_: &mut XPAPI| *stored_read.borrow() * 2.0,
+                    move |_: &mut XPAPI, value: f32| *stored_write.borrow_mut() = value,
+                ),
+            )
+            .unwrap();
+        let (read, write, refcon) = callbacks.borrow_mut().take().unwrap();
+        assert_eq!(unsafe { read(refcon) }, 3.0);
+        unsafe {
+            write(refcon, 4.0);
+        }
+        assert_eq!(*stored.borrow(), 4.0);
+        assert_eq!(unsafe { read(refcon) }, 8.0);
+        drop(computed);
+    }
+
+    /// The handler for `test_array_bounds`
+    type ArrayClosure = fn(&mut XPAPI) -> Vec<i32>;
+
+    #[test]
+    fn test_array_bounds() {
+        let mut handler: ArrayClosure = |_| vec![1, 2, 3];
+        let refcon = ptr::addr_of_mut!(handler).cast::<c_void>();
+        let mut dest = [0; 2];
+        let read = |offset, max, dest: &mut [c_int]| unsafe {
+            array_read::<[i32], ArrayClosure, c_int>(refcon, dest.as_mut_ptr(), offset, max)
+        };
+        assert_eq!(read(1, 2, &mut dest), 2);
+        assert_eq!(dest, [2, 3]);
+        // Negative offsets and lengths from C are ignored
+        assert_eq!(read(-1, 2, &mut dest), 0);
+        assert_eq!(read(0, -1, &mut dest), 0);
+    }
+}
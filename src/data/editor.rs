@@ -0,0 +1,201 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    cell::RefCell,
+    ffi::{c_void, CStr, CString},
+};
+
+use crate::{
+    flight_loop::{FlightLoop, FlightLoopPhase, LoopResult, LoopState},
+    make_x,
+    message::MessageId,
+    XPAPI,
+};
+
+/// The message that DataRefEditor and DataRefTool accept to add a dataref.
+/// The parameter is a pointer to the NUL-terminated dataref name.
+pub const MSG_ADD_DATAREF: i32 = 0x0100_0000;
+
+/// The signatures of the plugins that accept [`MSG_ADD_DATAREF`]
+pub const EDITOR_SIGNATURES: [&str; 2] = [
+    "xplanesdk.examples.DataRefEditor",
+    "com.leecbaker.datareftool",
+];
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = const { RefCell::new(Registry::new()) };
+}
+
+struct Registry {
+    enabled: bool,
+    /// True once the editors are expected to be loaded
+    ready: bool,
+    /// Names waiting to be announced
+    pending: Vec<CString>,
+    /// The flight loop that marks the registry as ready
+    flight_loop: Option<FlightLoop<()>>,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Registry {
+            enabled: false,
+            ready: false,
+            pending: Vec::new(),
+            flight_loop: None,
+        }
+    }
+}
+
+/// Turns registration on or off.
+pub(super) fn set_enabled(enabled: bool) {
+    let old_loop = REGISTRY.with_borrow_mut(|registry| {
+        registry.enabled = enabled;
+        if !enabled {
+            registry.pending.clear();
+            return registry.flight_loop.take();
+        }
+        if !registry.ready && registry.flight_loop.is_none() {
+            let callback = |_x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+                flush();
+                LoopResult::Deactivate
+            };
+            let mut flight_loop = FlightLoop::new(FlightLoopPhase::BeforeFlightModel, callback, ());
+            flight_loop.schedule_immediate();
+            registry.flight_loop = Some(flight_loop);
+        }
+        None
+    });
+    // The flight loop is dropped outside of the borrow.
+    drop(old_loop);
+}
+
+/// Returns true if registration is on.
+pub(super) fn is_enabled() -> bool {
+    REGISTRY.with_borrow(|registry| registry.enabled)
+}
+
+/// Announces a dataref created by this plugin, or queues it if the editors may not be loaded.
+pub(crate) fn announce(name: &CStr) {
+    let send_now = REGISTRY.with_borrow_mut(|registry| {
+        if !registry.enabled {
+            return false;
+        }
+        if !registry.ready {
+            registry.pending.push(name.to_owned());
+        }
+        registry.ready
+    });
+    if send_now {
+        send(&mut make_x(), name);
+    }
+}
+
+/// Sends all queued announcements, and sends later ones immediately.
+pub(crate) fn flush() {
+    let pending = REGISTRY.with_borrow_mut(|registry| {
+        if !registry.enabled {
+            return Vec::new();
+        }
+        registry.ready = true;
+        std::mem::take(&mut registry.pending)
+    });
+    let mut x = make_x();
+    for name in pending {
+        send(&mut x, &name);
+    }
+}
+
+/// Handles a message sent to this plugin.
+pub(crate) fn handle_message(message: MessageId, param: *mut c_void) {
+    // The user's plane has ID 0.
+    if message == MessageId::PlaneLoaded && param.is_null() {
+        flush();
+    }
+}
+
+/// Stops registration and frees its resources. Called when the plugin stops.
+pub(crate) fn shutdown() {
+    set_enabled(false);
+    REGISTRY.with_borrow_mut(|registry| registry.ready = false);
+}
+
+fn send(x: &mut XPAPI, name: &CStr) {
+    for signature in EDITOR_SIGNATURES {
+        if let Some(mut plugin) = x.plugins.from_signature(signature) {
+            // Safety: Both editors copy the name before returning.
+            unsafe {
+                plugin.send_message(
+                    MessageId::from(MSG_ADD_DATAREF),
+                    name.as_ptr().cast_mut().cast(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, ffi::c_char, ptr::NonNull, rc::Rc};
+
+    use xplane_sys::XPLMPluginID;
+
+    use super::*;
+
+    #[test]
+    fn test_queued_announcements() {
+        let loop_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let create_ctx = xplane_sys::XPLMCreateFlightLoop_context();
+        create_ctx.expect().once().return_once_st(move |_| loop_ptr);
+        let schedule_ctx = xplane_sys::XPLMScheduleFlightLoop_context();
+        schedule_ctx.expect().returning_st(|_, _, _| ());
+        let destroy_ctx = xplane_sys::XPLMDestroyFlightLoop_context();
+        destroy_ctx.expect().once().return_once_st(|_| ());
+        let find_ctx = xplane_sys::XPLMFindPluginBySignature_context();
+        find_ctx.expect().returning_st(|signature| {
+            let signature = unsafe { CStr::from_ptr(signature) };
+            if signature.to_bytes() == b"com.leecbaker.datareftool" {
+                7
+            } else {
+                xplane_sys::XPLM_NO_PLUGIN_ID
+            }
+        });
+        let info_ctx = xplane_sys::XPLMGetPluginInfo_context();
+        info_ctx.expect().returning_st(|_, _, _, _, _| ());
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let sent_1 = sent.clone();
+        let send_ctx = xplane_sys::XPLMSendMessageToPlugin_context();
+        send_ctx
+            .expect()
+            .returning_st(move |id: XPLMPluginID, message, param: *mut c_void| {
+                assert_eq!(id, 7);
+                assert_eq!(message, MSG_ADD_DATAREF);
+                let name = unsafe { CStr::from_ptr(param.cast::<c_char>()) };
+                sent_1.borrow_mut().push(name.to_owned());
+            });
+
+        // Not enabled: nothing is queued.
+        announce(&CString::new("xplane_rs/test/ignored").unwrap());
+        set_enabled(true);
+        assert!(is_enabled());
+        announce(&CString::new("xplane_rs/test/first").unwrap());
+        assert!(sent.borrow().is_empty());
+
+        // Messages about other planes don't count.
+        handle_message(MessageId::PlaneLoaded, 1 as *mut c_void);
+        assert!(sent.borrow().is_empty());
+        handle_message(MessageId::PlaneLoaded, std::ptr::null_mut());
+        assert_eq!(
+            *sent.borrow(),
+            [CString::new("xplane_rs/test/first").unwrap()]
+        );
+
+        announce(&CString::new("xplane_rs/test/second").unwrap());
+        assert_eq!(sent.borrow().len(), 2);
+
+        shutdown();
+        assert!(!is_enabled());
+    }
+}
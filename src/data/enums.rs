@@ -0,0 +1,115 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use num_enum::TryFromPrimitive;
+use snafu::prelude::*;
+use xplane_sys::XPLMDataTypeID;
+
+use super::{DataType, ScalarType};
+
+/// An int dataref whose values are the variants of the enum `E`
+///
+/// `E` should derive [`num_enum::TryFromPrimitive`] and [`num_enum::IntoPrimitive`].
+/// Reading a dataref of this type returns `Result<E, InvalidValue>` through
+/// [`EnumRead`](super::EnumRead), because X-Plane and other plugins can store values that
+/// are not variants of `E`.
+///
+/// ```no_run
+/// use num_enum::{IntoPrimitive, TryFromPrimitive};
+/// use xplane::data::{enums::IntEnum, DataApi, EnumRead};
+///
+/// #[derive(Debug, Clone, Copy, TryFromPrimitive, IntoPrimitive)]
+/// #[repr(i32)]
+/// enum GearHandle {
+///     Up = 0,
+///     Down = 1,
+/// }
+///
+/// fn gear_down(data: &mut DataApi) -> bool {
+///     let handle = data
+///         .find::<IntEnum<GearHandle>, _>("sim/cockpit2/controls/gear_handle_down")
+///         .unwrap();
+///     matches!(handle.get(), Ok(GearHandle::Down))
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IntEnum<E>(pub E);
+
+impl<E> DataType for IntEnum<E>
+where
+    E: TryFromPrimitive + Into<E::Primitive> + Copy,
+    E::Primitive: ScalarType,
+{
+    type Storage = i32;
+    fn sim_type() -> XPLMDataTypeID {
+        XPLMDataTypeID::Int
+    }
+    fn to_storage(&self) -> Self::Storage {
+        to_raw(self.0)
+    }
+}
+
+/// Converts an enum value to the int that X-Plane stores
+pub(super) fn to_raw<E>(value: E) -> i32
+where
+    E: TryFromPrimitive + Into<E::Primitive>,
+    E::Primitive: ScalarType,
+{
+    value.into().to_int()
+}
+
+/// Converts an int from X-Plane to an enum value
+pub(super) fn from_raw<E>(value: i32) -> Result<E, InvalidValue>
+where
+    E: TryFromPrimitive,
+    E::Primitive: ScalarType,
+{
+    let primitive = E::Primitive::from_int(value);
+    if primitive.to_int() != value {
+        return InvalidValueSnafu { value }.fail();
+    }
+    E::try_from_primitive(primitive).map_err(|_| InvalidValue { value })
+}
+
+/// An error returned when an enum dataref contains a value that is not a variant of its enum
+#[derive(Snafu, Debug, Clone, Copy, PartialEq, Eq)]
+#[snafu(display("{value} is not a valid value for this dataref"))]
+pub struct InvalidValue {
+    /// The value that was read
+    value: i32,
+}
+
+impl InvalidValue {
+    /// Returns the value that was read from the dataref
+    #[must_use]
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_enum::IntoPrimitive;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+    #[repr(u8)]
+    enum ViewType {
+        Forward = 0,
+        Chase = 2,
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(from_raw::<ViewType>(2), Ok(ViewType::Chase));
+        assert_eq!(from_raw::<ViewType>(0), Ok(ViewType::Forward));
+        assert_eq!(from_raw::<ViewType>(1).unwrap_err().value(), 1);
+        // Would wrap around to Chase if truncated to a u8
+        assert_eq!(from_raw::<ViewType>(258).unwrap_err().value(), 258);
+        assert_eq!(from_raw::<ViewType>(-1).unwrap_err().value(), -1);
+        assert_eq!(to_raw(ViewType::Chase), 2);
+        assert_eq!(IntEnum(ViewType::Chase).to_storage(), 2);
+    }
+}
@@ -0,0 +1,262 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    cell::RefCell,
+    ffi::c_void,
+    fmt,
+    marker::PhantomData,
+    rc::{Rc, Weak},
+};
+
+use snafu::prelude::*;
+use xplane_sys::XPLMDataRef;
+
+use crate::message::MessageId;
+
+use super::{
+    borrowed::{DataRef, FindError},
+    Access, DataRead, DataReadWrite, DataType, ReadOnly, ReadWrite,
+};
+
+thread_local! {
+    /// Lazy datarefs that have not been found yet
+    static PENDING: RefCell<Vec<Weak<dyn Resolve>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A dataref that is found the first time it is needed
+///
+/// T is the data type stored in the dataref.
+///
+/// A is the access level (`ReadOnly` or `ReadWrite`)
+///
+/// Datarefs created by other plugins often do not exist yet when
+/// [`Plugin::start`](crate::plugin::Plugin::start) runs. Until the dataref is found, every
+/// access tries to find it again, and returns [`NotResolved`] if it still cannot be found. Once
+/// found, the dataref is never looked up again.
+///
+/// Lazy datarefs also look for their datarefs again whenever
+/// [`MessageId::DatarefsAdded`](crate::message::MessageId::DatarefsAdded) is received
+/// (which requires the `XPLM_WANTS_DATAREF_NOTIFICATIONS` feature) and whenever the user's
+/// plane is loaded.
+pub struct LazyDataRef<T: ?Sized, A = ReadOnly> {
+    inner: Rc<RefCell<LazyInner<T, A>>>,
+}
+
+struct LazyInner<T: ?Sized, A> {
+    name: String,
+    id: Option<XPLMDataRef>,
+    /// Whether the dataref is accessed as a double but only has a float value
+    float_fallback: bool,
+    _phantom: PhantomData<(*mut (), A, T)>,
+}
+
+/// An error returned when a [`LazyDataRef`] is accessed before its dataref can be found
+#[derive(Snafu, Debug)]
+#[snafu(display("DataRef {name} has not been found: {source}"))]
+pub struct NotResolved {
+    /// The name of the dataref
+    name: String,
+    /// Why the dataref could not be found
+    source: FindError,
+}
+
+impl NotResolved {
+    /// Returns the name of the dataref.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the reason the dataref could not be found.
+    #[must_use]
+    pub fn reason(&self) -> &FindError {
+        &self.source
+    }
+}
+
+impl<T: DataType + ?Sized + 'static, A: Access + 'static> LazyDataRef<T, A> {
+    pub(super) fn new<S: Into<String>>(name: S) -> Self {
+        let inner = Rc::new(RefCell::new(LazyInner {
+            name: name.into(),
+            id: None,
+            float_fallback: false,
+            _phantom: PhantomData,
+        }));
+        let pending: Rc<dyn Resolve> = inner.clone();
+        PENDING.with_borrow_mut(|list| list.push(Rc::downgrade(&pending)));
+        LazyDataRef { inner }
+    }
+
+    /// Returns the name of the dataref.
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.inner.borrow().name.clone()
+    }
+
+    /// Returns true if the dataref has been found.
+    ///
+    /// This does not try to find the dataref.
+    #[must_use]
+    pub fn is_resolved(&self) -> bool {
+        self.inner.borrow().id.is_some()
+    }
+
+    /// Tries to find the dataref, if it has not been found yet.
+    /// # Errors
+    /// Returns an error if the dataref does not exist, has the wrong type, or cannot be
+    /// written when `A` is [`ReadWrite`].
+    pub fn resolve(&self) -> Result<(), NotResolved> {
+        self.inner.borrow_mut().resolve().map(|_| ())
+    }
+
+    /// Returns a handle to the dataref, trying to find it first if needed.
+    /// # Errors
+    /// Returns an error if the dataref has not been found and still cannot be found.
+    pub fn dataref(&self) -> Result<DataRef<T, A>, NotResolved> {
+        let mut inner = self.inner.borrow_mut();
+        let id = inner.resolve()?;
+        Ok(DataRef {
+            id,
+            float_fallback: inner.float_fallback,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: DataType + 'static, A: Access + 'static> LazyDataRef<T, A>
+where
+    DataRef<T, A>: DataRead<T>,
+{
+    /// Reads the value of the dataref, trying to find it first if needed.
+    /// # Errors
+    /// Returns an error if the dataref has not been found and still cannot be found.
+    pub fn get(&self) -> Result<T, NotResolved> {
+        Ok(self.dataref()?.get())
+    }
+}
+
+impl<T: DataType + 'static> LazyDataRef<T, ReadWrite>
+where
+    DataRef<T, ReadWrite>: DataReadWrite<T>,
+{
+    /// Writes a value to the dataref, trying to find it first if needed.
+    /// # Errors
+    /// Returns an error if the dataref has not been found and still cannot be found.
+    /// The value is not stored for later.
+    pub fn set(&mut self, value: T) -> Result<(), NotResolved> {
+        self.dataref()?.set(value);
+        Ok(())
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<T: ?Sized, A> fmt::Debug for LazyDataRef<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("LazyDataRef")
+            .field("name", &inner.name)
+            .field("resolved", &inner.id.is_some())
+            .finish()
+    }
+}
+
+impl<T: DataType + ?Sized, A: Access> LazyInner<T, A> {
+    /// Finds the dataref if it has not been found yet, and returns its handle
+    fn resolve(&mut self) -> Result<XPLMDataRef, NotResolved> {
+        if let Some(id) = self.id {
+            return Ok(id);
+        }
+        let dataref = self.find().context(NotResolvedSnafu { name: &self.name })?;
+        self.id = Some(dataref.id);
+        self.float_fallback = dataref.float_fallback;
+        Ok(dataref.id)
+    }
+
+    fn find(&self) -> Result<DataRef<T, ReadOnly>, FindError> {
+        if A::writeable() {
+            Ok(DataRef::<T, ReadWrite>::find_writable(&self.name)?.cast())
+        } else {
+            DataRef::<T, ReadOnly>::find(&self.name)
+        }
+    }
+}
+
+/// A lazy dataref of any type that can be found again
+trait Resolve {
+    /// Tries to find the dataref. Returns true if it has been found.
+    fn retry(&self) -> bool;
+}
+
+impl<T: DataType + ?Sized, A: Access> Resolve for RefCell<LazyInner<T, A>> {
+    fn retry(&self) -> bool {
+        self.borrow_mut().resolve().is_ok()
+    }
+}
+
+/// Looks for the datarefs of all lazy datarefs that have not found them yet.
+fn retry_pending() {
+    let pending = PENDING.with_borrow_mut(std::mem::take);
+    let still_pending = pending
+        .into_iter()
+        .filter(|lazy| lazy.upgrade().is_some_and(|lazy| !lazy.retry()))
+        .collect::<Vec<_>>();
+    PENDING.with_borrow_mut(|pending| pending.extend(still_pending));
+}
+
+/// Handles a message sent to this plugin.
+pub(crate) fn handle_message(message: MessageId, param: *mut c_void) {
+    #[cfg(feature = "XPLM400")]
+    if message == MessageId::DatarefsAdded {
+        retry_pending();
+        return;
+    }
+    // The user's plane has ID 0.
+    if message == MessageId::PlaneLoaded && param.is_null() {
+        retry_pending();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, ptr};
+
+    use super::*;
+
+    #[test]
+    fn test_unresolved() {
+        let finds = Rc::new(Cell::new(0));
+        let finds_1 = finds.clone();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().returning_st(move |_| {
+            finds_1.set(finds_1.get() + 1);
+            ptr::null_mut()
+        });
+
+        let mut lazy = LazyDataRef::<f32, ReadWrite>::new("otherplugin/engines/n1");
+        assert!(!lazy.is_resolved());
+        // Nothing is looked up until the dataref is needed
+        assert_eq!(finds.get(), 0);
+
+        let error = lazy.get().unwrap_err();
+        assert_eq!(error.name(), "otherplugin/engines/n1");
+        assert!(matches!(error.reason(), FindError::NotFound));
+        assert!(matches!(
+            lazy.set(1.0).unwrap_err().reason(),
+            FindError::NotFound
+        ));
+        assert_eq!(finds.get(), 2);
+
+        handle_message(MessageId::PlaneLoaded, ptr::null_mut());
+        assert_eq!(finds.get(), 3);
+        // Other planes do not trigger a retry
+        handle_message(MessageId::PlaneLoaded, ptr::NonNull::dangling().as_ptr());
+        assert_eq!(finds.get(), 3);
+        assert!(!lazy.is_resolved());
+
+        drop(lazy);
+        handle_message(MessageId::PlaneLoaded, ptr::null_mut());
+        assert_eq!(finds.get(), 3);
+    }
+}
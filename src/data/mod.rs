@@ -4,28 +4,86 @@
 
 use std::{
     ffi::{CString, NulError},
+    slice,
     string::FromUtf8Error,
 };
 
+#[cfg(feature = "XPLM400")]
+use std::{ffi::c_int, marker::PhantomData};
+
+#[cfg(feature = "XPLM400")]
+use xplane_sys::XPLMCountDataRefs;
 use xplane_sys::XPLMDataTypeID;
 
 use crate::{
     data::shared::{SharedData, SharedDataError, SharedDataHandler},
     ffi::StringBuffer,
+    flight_loop::{FlightLoopPhase, LoopResult},
     NoSendSync,
 };
 
+#[cfg(feature = "XPLM400")]
+use self::borrowed::DataRefs;
 use self::{
-    borrowed::{DataRef, FindError},
-    owned::{CreateError, OwnedData},
+    borrowed::{AnyDataRef, DataRef, FindError},
+    cache::DataCache,
+    computed::{ComputedArrayHandler, ComputedData, ComputedHandler},
+    enums::InvalidValue,
+    lazy::LazyDataRef,
+    overrides::{HeldOverride, OverrideGuard},
+    owned::{CreateError, FixedArray, OverflowError, OverflowPolicy, OwnedData},
+    proxy::{ProxyData, ProxyTarget},
+    recording::{Player, Recorder, RecorderError, Recording},
+    watcher::DataWatcher,
 };
 
 /// Datarefs created by X-Plane or other plugins
 pub mod borrowed;
+/// Finding several datarefs at once
+pub mod bundle;
+/// Per-frame snapshots of datarefs
+pub mod cache;
+/// Datarefs created by this plugin, with values computed on demand
+pub mod computed;
+/// Announcing datarefs to DataRefEditor and DataRefTool
+pub mod editor;
+/// Int datarefs holding enumerations
+pub mod enums;
+/// Datarefs that are found when they are first needed
+pub mod lazy;
+/// Taking over X-Plane systems through override datarefs
+pub mod overrides;
 /// Datarefs created by this plugin
 pub mod owned;
+/// Datarefs that forward to another dataref through transforms
+pub mod proxy;
+/// Recording datarefs to files, and playing recordings back
+pub mod recording;
 /// Datarefs shared between plugins.
 pub mod shared;
+/// Unit-aware value types for datarefs
+pub mod units;
+/// Watching datarefs for changes
+pub mod watcher;
+
+pub use self::bundle::{DataRefBundle, FindAllError};
+/// Derives [`DataRefBundle`] for a struct of datarefs.
+///
+/// ```no_run
+/// use xplane::data::{borrowed::DataRef, DataRefs, ReadOnly, ReadWrite};
+///
+/// #[derive(DataRefs)]
+/// struct Position {
+///     #[dataref("sim/flightmodel/position/latitude")]
+///     latitude: DataRef<f64, ReadOnly>,
+///     #[dataref("sim/flightmodel/position/longitude")]
+///     longitude: DataRef<f64, ReadOnly>,
+///     #[dataref("sim/flightmodel/position/local_y", writable)]
+///     local_y: DataRef<f64, ReadWrite>,
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use xplane_derive::DataRefs;
 
 /// Marks a dataref as readable
 pub enum ReadOnly {}
@@ -63,6 +121,24 @@ pub trait DataReadWrite<T>: DataRead<T> {
     fn set(&mut self, value: T);
 }
 
+/// Trait for data accessors that hold enum values
+pub trait EnumRead<E> {
+    /// Reads a value
+    ///
+    /// # Errors
+    /// Returns an error if the dataref contains a value that is not a variant of `E`.
+    fn get(&self) -> Result<E, InvalidValue>;
+
+    /// Reads the value as X-Plane stores it, without converting it to `E`
+    fn get_raw(&self) -> i32;
+}
+
+/// Trait for writable data accessors that hold enum values
+pub trait EnumReadWrite<E>: EnumRead<E> {
+    /// Writes a value
+    fn set(&mut self, value: E);
+}
+
 /// Trait for readable array data accessors
 pub trait ArrayRead<T: ArrayType + ?Sized> {
     /// Reads values
@@ -81,7 +157,39 @@ pub trait ArrayRead<T: ArrayType + ?Sized> {
     /// If the return value is outside of the range of [`usize`], this function
     /// will panic.
     /// This should not be possible.
-    fn get(&self, dest: &mut [T::Element]) -> usize;
+    fn get(&self, dest: &mut [T::Element]) -> usize {
+        self.get_range(0, dest)
+    }
+
+    /// Reads values, starting at the element at index `offset`
+    ///
+    /// Values are stored in the provided slice. Up to `dest.len()` values are read,
+    /// stopping at the end of the dataref. If `offset` is at or beyond the end of the
+    /// dataref, nothing is read.
+    ///
+    /// This function returns the number of values that were read.
+    ///
+    /// # Panics
+    /// If the return value is outside of the range of [`usize`], this function
+    /// will panic.
+    /// This should not be possible.
+    fn get_range(&self, offset: usize, dest: &mut [T::Element]) -> usize;
+
+    /// Reads the single element at `index`
+    ///
+    /// Returns [`None`] if `index` is beyond the end of the dataref.
+    fn get_at(&self, index: usize) -> Option<T::Element>
+    where
+        T::Element: Default,
+    {
+        let mut value = [T::Element::default()];
+        if self.get_range(index, &mut value) == 1 {
+            let [value] = value;
+            Some(value)
+        } else {
+            None
+        }
+    }
 
     /// Returns the length of the data array
     ///
@@ -115,11 +223,31 @@ pub trait ArrayReadWrite<T: ArrayType + ?Sized>: ArrayRead<T> {
     /// the provided slice, values beyond the bounds of the slice are not changed.
     ///
     /// If the dataref is smaller than the provided slice, the values beyond the
-    /// dataref bounds will be ignored.
-    fn set(&mut self, values: &[T::Element]);
+    /// dataref bounds will be ignored. Owned datarefs can instead grow to fit; see
+    /// [`OverflowPolicy`](owned::OverflowPolicy).
+    fn set(&mut self, values: &[T::Element]) {
+        self.set_range(0, values);
+    }
+
+    /// Writes values, starting at the element at index `offset`
+    ///
+    /// Only the elements from `offset` to `offset + values.len()` are changed, so
+    /// elements outside of that range that are written by X-Plane or other plugins are
+    /// left alone. Values beyond the dataref bounds will be ignored.
+    fn set_range(&mut self, offset: usize, values: &[T::Element]);
+
+    /// Writes the single element at `index`
+    ///
+    /// If `index` is beyond the end of the dataref, nothing is written.
+    fn set_at(&mut self, index: usize, value: T::Element) {
+        self.set_range(index, slice::from_ref(&value));
+    }
 }
 
 /// Trait for data accessors that can be read as strings
+///
+/// This reads byte array datarefs. Accessors for string datarefs found or created as
+/// [`str`] implement [`StrRead`] instead, which can also decode strings lossily.
 pub trait StringRead {
     /// Reads the value of this dataref and appends it to the provided string
     ///
@@ -175,6 +303,99 @@ where
     }
 }
 
+/// Trait for string data accessors
+///
+/// Strings end at the first NUL byte, or at the end of the dataref if it has none.
+pub trait StrRead {
+    /// Reads every byte of this dataref, including any NUL bytes after the string
+    fn get_bytes(&self) -> Vec<u8>;
+
+    /// Returns the length of this dataref in bytes, which is the longest string it can hold
+    fn max_len(&self) -> usize;
+
+    /// Reads the string, replacing any invalid UTF-8 with U+FFFD
+    fn get(&self) -> String {
+        let mut bytes = self.get_bytes();
+        bytes.truncate(str_len(&bytes));
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// Reads the string
+    ///
+    /// # Errors
+    /// Returns an error if the string is not valid UTF-8.
+    fn get_strict(&self) -> Result<String, FromUtf8Error> {
+        let mut bytes = self.get_bytes();
+        bytes.truncate(str_len(&bytes));
+        String::from_utf8(bytes)
+    }
+}
+
+/// Trait for writable string data accessors
+pub trait StrReadWrite: StrRead {
+    /// Writes a string, and fills the rest of the dataref with NUL bytes
+    ///
+    /// If the string is longer than the dataref, `policy` decides what happens. Strings are
+    /// truncated at a character boundary, so the dataref never ends with a partial character.
+    /// Only owned datarefs can grow; for other datarefs, [`OverflowPolicy::Grow`](owned::OverflowPolicy::Grow)
+    /// returns an error like [`OverflowPolicy::Error`](owned::OverflowPolicy::Error).
+    ///
+    /// # Errors
+    /// Returns an error if the string does not fit and cannot be truncated or grown.
+    fn set_with_policy(&mut self, value: &str, policy: OverflowPolicy)
+        -> Result<(), OverflowError>;
+
+    /// Writes a string, and fills the rest of the dataref with NUL bytes
+    ///
+    /// Owned datarefs follow their [`OverflowPolicy`](owned::OverflowPolicy) if the string
+    /// does not fit. Other datarefs truncate it.
+    fn set(&mut self, value: &str) {
+        let _ = self.set_with_policy(value, OverflowPolicy::Truncate);
+    }
+}
+
+/// Returns true if `types` includes [`XPLMDataTypeID::Data`]
+///
+/// `XPLMDataTypeID::data` checks the bit for int arrays, so this checks the flag directly.
+fn has_data_type(types: XPLMDataTypeID) -> bool {
+    types & XPLMDataTypeID::Data == XPLMDataTypeID::Data
+}
+
+/// Returns the length of the string in `bytes`, which ends at the first NUL byte
+fn str_len(bytes: &[u8]) -> usize {
+    bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len())
+}
+
+/// Lays out `value` for a string dataref that is `len` bytes long, following `policy` if it
+/// does not fit
+///
+/// The returned bytes are zero-filled to `len`, or longer if the policy is
+/// [`OverflowPolicy::Grow`].
+fn str_bytes(value: &str, len: usize, policy: OverflowPolicy) -> Result<Vec<u8>, OverflowError> {
+    let mut end = value.len();
+    if end > len {
+        match policy {
+            OverflowPolicy::Truncate => {
+                end = len;
+                while !value.is_char_boundary(end) {
+                    end -= 1;
+                }
+            }
+            OverflowPolicy::Grow => return Ok(value.as_bytes().to_vec()),
+            OverflowPolicy::Error => {
+                return Err(OverflowError {
+                    offset: 0,
+                    count: value.len(),
+                    len,
+                })
+            }
+        }
+    }
+    let mut bytes = vec![0; len];
+    bytes[..end].copy_from_slice(&value.as_bytes()[..end]);
+    Ok(bytes)
+}
+
 /// Marker for types that can be used with datarefs
 pub trait DataType {
     /// The type that should be used to store data of this type
@@ -187,6 +408,12 @@ pub trait DataType {
     /// Creates an instance of a storage type from an instance of self
     #[doc(hidden)]
     fn to_storage(&self) -> Self::Storage;
+    /// Returns the number of elements, if this is a fixed-length array type
+    #[doc(hidden)]
+    #[must_use]
+    fn fixed_len() -> Option<usize> {
+        None
+    }
 }
 
 /// Marker for types that are arrays
@@ -196,6 +423,20 @@ pub trait ArrayType: DataType {
 }
 
 macro_rules! impl_type {
+    ([$native_type:ty; N] as $sim_type:path) => {
+        impl<const N: usize> DataType for [$native_type; N] {
+            type Storage = FixedArray<$native_type, N>;
+            fn sim_type() -> XPLMDataTypeID {
+                $sim_type
+            }
+            fn to_storage(&self) -> Self::Storage {
+                FixedArray::new(*self)
+            }
+            fn fixed_len() -> Option<usize> {
+                Some(N)
+            }
+        }
+    };
     ([$native_type:ty] as $sim_type:path) => {
         impl DataType for [$native_type] {
             type Storage = Vec<$native_type>;
@@ -223,6 +464,8 @@ macro_rules! impl_type {
     };
 }
 
+pub(crate) use impl_type;
+
 impl_type!(bool as XPLMDataTypeID::Int);
 impl_type!(u8 as XPLMDataTypeID::Int);
 impl_type!(i8 as XPLMDataTypeID::Int);
@@ -237,6 +480,99 @@ impl_type!([u32] as XPLMDataTypeID::IntArray);
 impl_type!([f32] as XPLMDataTypeID::FloatArray);
 impl_type!([u8] as XPLMDataTypeID::Data);
 impl_type!([i8] as XPLMDataTypeID::Data);
+impl_type!([i32; N] as XPLMDataTypeID::IntArray);
+impl_type!([f32; N] as XPLMDataTypeID::FloatArray);
+impl_type!([u8; N] as XPLMDataTypeID::Data);
+
+impl DataType for str {
+    type Storage = Vec<u8>;
+    fn sim_type() -> XPLMDataTypeID {
+        XPLMDataTypeID::Data
+    }
+    fn to_storage(&self) -> Self::Storage {
+        self.as_bytes().to_vec()
+    }
+}
+
+/// Marker for single-value types that can be converted to and from the
+/// int, float and double types that X-Plane uses
+pub trait ScalarType: DataType<Storage = Self> + Copy + 'static {
+    /// Converts this value to an int
+    #[doc(hidden)]
+    fn to_int(self) -> i32;
+    /// Converts this value to a float
+    #[doc(hidden)]
+    fn to_float(self) -> f32;
+    /// Converts this value to a double
+    #[doc(hidden)]
+    fn to_double(self) -> f64;
+    /// Converts an int to this type
+    #[doc(hidden)]
+    fn from_int(value: i32) -> Self;
+    /// Converts a float to this type
+    #[doc(hidden)]
+    fn from_float(value: f32) -> Self;
+    /// Converts a double to this type
+    #[doc(hidden)]
+    fn from_double(value: f64) -> Self;
+}
+
+macro_rules! impl_scalar {
+    ($($native_type:ty),*) => {
+        $(
+            #[allow(
+                clippy::cast_lossless,
+                clippy::cast_possible_truncation,
+                clippy::cast_possible_wrap,
+                clippy::cast_precision_loss,
+                clippy::cast_sign_loss
+            )]
+            impl ScalarType for $native_type {
+                fn to_int(self) -> i32 {
+                    self as i32
+                }
+                fn to_float(self) -> f32 {
+                    self as f32
+                }
+                fn to_double(self) -> f64 {
+                    self as f64
+                }
+                fn from_int(value: i32) -> Self {
+                    value as Self
+                }
+                fn from_float(value: f32) -> Self {
+                    value as Self
+                }
+                fn from_double(value: f64) -> Self {
+                    value as Self
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(u8, i8, u16, i16, u32, i32, f32, f64);
+
+impl ScalarType for bool {
+    fn to_int(self) -> i32 {
+        i32::from(self)
+    }
+    fn to_float(self) -> f32 {
+        f32::from(u8::from(self))
+    }
+    fn to_double(self) -> f64 {
+        f64::from(u8::from(self))
+    }
+    fn from_int(value: i32) -> Self {
+        value != 0
+    }
+    fn from_float(value: f32) -> Self {
+        value != 0.0
+    }
+    fn from_double(value: f64) -> Self {
+        value != 0.0
+    }
+}
 
 /// Access struct for X-Plane's data APIs.
 pub struct DataApi {
@@ -245,6 +581,8 @@ pub struct DataApi {
 
 impl DataApi {
     /// Finds a readable dataref by its name.
+    ///
+    /// See [`DataRef`] for the dataref types that each type `T` can be found as.
     /// # Errors
     /// Returns an error if the dataref does not exist or has the wrong type
     pub fn find<T: DataType + ?Sized, S: AsRef<str>>(
@@ -254,6 +592,102 @@ impl DataApi {
         DataRef::find(name)
     }
 
+    /// Finds a writable dataref by its name.
+    ///
+    /// This is the same as [`find`](Self::find) followed by
+    /// [`DataRef::writeable`], but returns [`FindError::NotWritable`] if the dataref cannot
+    /// be written.
+    /// # Errors
+    /// Returns an error if the dataref does not exist, has the wrong type, or cannot be written
+    pub fn find_writable<T: DataType + ?Sized, S: AsRef<str>>(
+        &mut self,
+        name: S,
+    ) -> Result<DataRef<T, ReadWrite>, FindError> {
+        DataRef::find_writable(name)
+    }
+
+    /// Creates a [`LazyDataRef`], which finds the dataref `name` the first time it is needed.
+    ///
+    /// This never fails. Errors are returned when the dataref is accessed instead.
+    pub fn find_lazy<T: DataType + ?Sized + 'static, A: Access + 'static, S: Into<String>>(
+        &mut self,
+        name: S,
+    ) -> LazyDataRef<T, A> {
+        LazyDataRef::new(name)
+    }
+
+    /// Finds every dataref in a [`DataRefBundle`].
+    /// # Errors
+    /// Returns an error listing every dataref that could not be found.
+    pub fn find_all<B: DataRefBundle>(&mut self) -> Result<B, FindAllError> {
+        B::find_all(self)
+    }
+
+    /// Finds a readable dataref by its name, without checking its type at compile time.
+    /// # Errors
+    /// Returns an error if the dataref does not exist or has no type this library recognizes.
+    pub fn find_any<S: AsRef<str>>(&mut self, name: S) -> Result<AnyDataRef<ReadOnly>, FindError> {
+        AnyDataRef::find(name)
+    }
+
+    /// Sets the override dataref `name` to 1, and returns a guard that restores its previous
+    /// value when dropped.
+    ///
+    /// If the dataref is an int array, every element is set.
+    /// # Errors
+    /// Returns an error if the dataref does not exist, is not an int or int array,
+    /// or cannot be written.
+    pub fn take_override<S: AsRef<str>>(&mut self, name: S) -> Result<OverrideGuard, FindError> {
+        OverrideGuard::take(name)
+    }
+
+    /// Sets the element at `index` of the override array dataref `name` to 1, and returns a
+    /// guard that restores its previous value when dropped.
+    /// # Errors
+    /// Returns an error if the dataref does not exist, is not an int array, cannot be written,
+    /// or has no element at `index`.
+    pub fn take_override_element<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        index: usize,
+    ) -> Result<OverrideGuard, FindError> {
+        OverrideGuard::take_element(name, index)
+    }
+
+    /// Returns the overrides held by [`OverrideGuard`]s that have not been released,
+    /// in the order they were taken.
+    pub fn held_overrides(&mut self) -> Vec<HeldOverride> {
+        overrides::held()
+    }
+
+    /// Returns the number of datarefs registered with X-Plane, including those
+    /// created by X-Plane itself and by other plugins.
+    #[cfg(feature = "XPLM400")]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn count_datarefs(&mut self) -> usize {
+        unsafe { XPLMCountDataRefs() as usize }
+    }
+
+    /// Returns an iterator over every dataref registered with X-Plane.
+    #[cfg(feature = "XPLM400")]
+    pub fn all_datarefs(&mut self) -> DataRefs {
+        self.datarefs_from(0)
+    }
+
+    /// Returns an iterator over the datarefs registered with X-Plane, starting at index `first`.
+    ///
+    /// Datarefs are never unregistered from the list, so this can be used with a cached
+    /// count to only look at datarefs added since [`MessageId::DatarefsAdded`](crate::message::MessageId::DatarefsAdded)
+    /// was last received.
+    #[cfg(feature = "XPLM400")]
+    pub fn datarefs_from(&mut self, first: usize) -> DataRefs {
+        DataRefs {
+            next: first.try_into().unwrap_or(c_int::MAX),
+            count: unsafe { XPLMCountDataRefs() },
+            _phantom: PhantomData,
+        }
+    }
+
     /// Creates a new dataref with the provided name containing the default value of `T`.
     /// # Errors
     /// Errors if there is a NUL character in the dataref name, or if a dataref with that name already exists.
@@ -277,6 +711,124 @@ impl DataApi {
         OwnedData::new_with_value(name, value)
     }
 
+    /// Creates a new dataref with the provided name and value, which X-Plane and other plugins
+    /// can read and write as an int, a float, or a double.
+    ///
+    /// Values are converted to and from `T` as needed, the same way X-Plane's own datarefs are.
+    /// Conversions to integers truncate toward zero.
+    /// # Errors
+    /// Errors if there is a NUL character in the dataref name, or if a dataref with that name already exists.
+    /// # Panics
+    /// Panics if the dataref ID returned from X-Plane is null. This should not occur.
+    pub fn new_owned_multi<T: ScalarType, A: Access, S: AsRef<str>>(
+        &mut self,
+        name: S,
+        value: T,
+    ) -> Result<OwnedData<T, A>, CreateError> {
+        OwnedData::new_multi(name, value)
+    }
+
+    /// Creates a new dataref with the provided name, which forwards reads and writes to another
+    /// dataref through the transforms in `target`.
+    ///
+    /// The target does not need to exist yet. See [`ProxyData`] for details.
+    /// # Errors
+    /// Errors if there is a NUL character in either dataref name, or if a dataref with the
+    /// provided name already exists.
+    /// # Panics
+    /// Panics if the dataref ID returned from X-Plane is null. This should not occur.
+    pub fn new_proxy<A: Access, S: AsRef<str>>(
+        &mut self,
+        name: S,
+        target: ProxyTarget,
+    ) -> Result<ProxyData<A>, CreateError> {
+        ProxyData::new(name, target)
+    }
+
+    /// Turns automatic announcement of datarefs created by this plugin to DataRefEditor and
+    /// DataRefTool on or off. It is off by default.
+    ///
+    /// These plugins only list datarefs that existed when they scanned X-Plane, or that another
+    /// plugin announces to them with a message. When registration is enabled, every
+    /// [`OwnedData`], [`ComputedData`] and [`SharedData`] created afterwards is announced.
+    ///
+    /// Announcements are queued until the editors are likely to be loaded: the first flight
+    /// loop after registration is enabled, or the user's plane being loaded, whichever comes
+    /// first. Later announcements are sent immediately.
+    pub fn set_editor_registration(&mut self, enabled: bool) {
+        editor::set_enabled(enabled);
+    }
+
+    /// Returns true if datarefs created by this plugin are announced to DataRefEditor and
+    /// DataRefTool.
+    #[must_use]
+    pub fn editor_registration(&mut self) -> bool {
+        editor::is_enabled()
+    }
+
+    /// Creates a new dataref with the provided name, whose value is computed by `handler`
+    /// every time X-Plane or another plugin reads it.
+    ///
+    /// If `A` is [`ReadWrite`], writes from X-Plane and other plugins are passed to the handler.
+    /// # Errors
+    /// Errors if there is a NUL character in the dataref name, or if a dataref with that name already exists.
+    /// # Panics
+    /// Panics if the dataref ID returned from X-Plane is null. This should not occur.
+    pub fn new_computed<T: ScalarType, A: Access, S: AsRef<str>>(
+        &mut self,
+        name: S,
+        handler: impl ComputedHandler<T>,
+    ) -> Result<ComputedData<T, A>, CreateError> {
+        ComputedData::new(name, handler)
+    }
+
+    /// Creates a new array dataref with the provided name, whose contents are computed by `handler`
+    /// every time X-Plane or another plugin reads it.
+    ///
+    /// If `A` is [`ReadWrite`], writes from X-Plane and other plugins are passed to the handler.
+    /// # Errors
+    /// Errors if there is a NUL character in the dataref name, or if a dataref with that name already exists.
+    /// # Panics
+    /// Panics if the dataref ID returned from X-Plane is null. This should not occur.
+    pub fn new_computed_array<T: ArrayType + ?Sized + 'static, A: Access, S: AsRef<str>>(
+        &mut self,
+        name: S,
+        handler: impl ComputedArrayHandler<T>,
+    ) -> Result<ComputedData<T, A>, CreateError> {
+        ComputedData::new_array(name, handler)
+    }
+
+    /// Creates a new [`DataWatcher`], which checks its datarefs from a flight loop
+    /// in `phase`, as often as `rate` specifies.
+    pub fn new_watcher(&mut self, phase: FlightLoopPhase, rate: LoopResult) -> DataWatcher {
+        DataWatcher::new(phase, rate)
+    }
+
+    /// Creates a new [`DataCache`], which refreshes its datarefs once per cycle from a flight
+    /// loop in `phase`.
+    pub fn new_cache(&mut self, phase: FlightLoopPhase) -> DataCache {
+        DataCache::new(phase)
+    }
+
+    /// Starts recording the datarefs with the provided names to `output`, from a flight loop
+    /// in `phase`.
+    /// # Errors
+    /// Returns an error if a dataref could not be found or the header could not be written.
+    pub fn new_recorder<W: std::io::Write + 'static, S: AsRef<str>>(
+        &mut self,
+        output: W,
+        names: &[S],
+        phase: FlightLoopPhase,
+    ) -> Result<Recorder<W>, RecorderError> {
+        Recorder::new(output, names, phase)
+    }
+
+    /// Creates a [`Player`] for a recording, which writes to datarefs from a flight loop
+    /// in `phase`.
+    pub fn new_player(&mut self, recording: Recording, phase: FlightLoopPhase) -> Player {
+        Player::new(recording, phase)
+    }
+
     /// Creates a new [`SharedData<T>`].
     /// The function in your handler will be called every time the dataref's value changes.
     /// # Errors
@@ -289,4 +841,16 @@ impl DataApi {
     ) -> Result<SharedData<T>, SharedDataError> {
         SharedData::new(name, handler)
     }
+
+    /// Creates a new [`SharedData<T>`] without a handler.
+    /// The value can still be read and written through the returned [`SharedData`].
+    /// # Errors
+    /// Returns an error if the dataref name contains a NUL byte, or if the type does not
+    /// match the existing dataref of that name.
+    pub fn new_shared_without_handler<S: Into<Vec<u8>>, T: DataType + ?Sized + 'static>(
+        &mut self,
+        name: S,
+    ) -> Result<SharedData<T>, SharedDataError> {
+        SharedData::new_without_handler(name)
+    }
 }
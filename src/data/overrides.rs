@@ -0,0 +1,368 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CString,
+    fmt,
+    marker::PhantomData,
+    rc::{Rc, Weak},
+};
+
+use xplane_sys::{XPLMCanWriteDataRef, XPLMDataRef, XPLMFindDataRef, XPLMGetDataRefTypes};
+
+use super::{
+    borrowed::{DataRef, FindError},
+    ArrayRead, ArrayReadWrite, DataRead, DataReadWrite, ReadWrite,
+};
+
+thread_local! {
+    /// Overrides taken by guards, in the order they were taken
+    static HELD: RefCell<Vec<Weak<OverrideState>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Holds an override dataref set to 1, and restores its previous value when dropped
+///
+/// Setting a `sim/operation/override/...` dataref to 1 tells X-Plane to stop simulating part
+/// of the aircraft, so that a plugin can drive it instead. If the override is never reset, the
+/// sim stays broken after the plugin is disabled.
+///
+/// Guards are created with [`DataApi::take_override`](super::DataApi::take_override) and
+/// [`DataApi::take_override_element`](super::DataApi::take_override_element).
+///
+/// The previous value is the one the dataref had when the guard was created. If several
+/// guards hold the same override, they should be dropped in the reverse order that they were
+/// created in.
+///
+/// All guards are also released when the plugin is disabled, after
+/// [`Plugin::disable`](crate::plugin::Plugin::disable) returns. A guard that has been released
+/// this way stays released, and does nothing when it is dropped.
+pub struct OverrideGuard {
+    state: Rc<OverrideState>,
+}
+
+/// Information about an override held by an [`OverrideGuard`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldOverride {
+    /// The name of the override dataref
+    name: String,
+    /// The index of the element, if only one element is held
+    index: Option<usize>,
+}
+
+impl HeldOverride {
+    /// Returns the name of the override dataref.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the index of the held element, or `None` if the whole dataref is held.
+    #[must_use]
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+}
+
+struct OverrideState {
+    name: String,
+    id: XPLMDataRef,
+    previous: Previous,
+    released: Cell<bool>,
+}
+
+/// The value of an override dataref before it was set
+enum Previous {
+    /// An int dataref
+    Scalar(i32),
+    /// Every element of an int array dataref
+    Array(Vec<i32>),
+    /// One element of an int array dataref
+    Element {
+        /// The index of the element
+        index: usize,
+        /// The previous value of the element
+        value: i32,
+    },
+}
+
+impl OverrideGuard {
+    /// Sets the whole override dataref `name` to 1.
+    pub(super) fn take<S: AsRef<str>>(name: S) -> Result<Self, FindError> {
+        let name = name.as_ref();
+        let id = find_writable(name)?;
+        let types = unsafe { XPLMGetDataRefTypes(id) };
+        let previous = if types.int() {
+            let mut dataref = handle::<i32>(id);
+            let previous = dataref.get();
+            dataref.set(1);
+            Previous::Scalar(previous)
+        } else if types.int_array() {
+            let mut dataref = handle::<[i32]>(id);
+            let mut previous = vec![0; dataref.len()];
+            dataref.get(&mut previous);
+            dataref.set(&vec![1; previous.len()]);
+            Previous::Array(previous)
+        } else {
+            return Err(FindError::WrongType);
+        };
+        Ok(Self::hold(name, id, previous))
+    }
+
+    /// Sets the element at `index` of the override array dataref `name` to 1.
+    pub(super) fn take_element<S: AsRef<str>>(name: S, index: usize) -> Result<Self, FindError> {
+        let name = name.as_ref();
+        let id = find_writable(name)?;
+        let types = unsafe { XPLMGetDataRefTypes(id) };
+        if !types.int_array() {
+            return Err(FindError::WrongType);
+        }
+        let mut dataref = handle::<[i32]>(id);
+        let len = dataref.len();
+        let Some(value) = dataref.get_at(index) else {
+            return Err(FindError::WrongLength {
+                expected: index + 1,
+                actual: len,
+            });
+        };
+        dataref.set_at(index, 1);
+        Ok(Self::hold(name, id, Previous::Element { index, value }))
+    }
+
+    fn hold(name: &str, id: XPLMDataRef, previous: Previous) -> Self {
+        let state = Rc::new(OverrideState {
+            name: name.to_owned(),
+            id,
+            previous,
+            released: Cell::new(false),
+        });
+        HELD.with_borrow_mut(|held| held.push(Rc::downgrade(&state)));
+        OverrideGuard { state }
+    }
+
+    /// Returns the name of the override dataref.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Returns the index of the held element, or `None` if the whole dataref is held.
+    #[must_use]
+    pub fn index(&self) -> Option<usize> {
+        self.state.index()
+    }
+
+    /// Returns true if the override has not been released yet.
+    ///
+    /// This is false after the plugin has been disabled.
+    #[must_use]
+    pub fn is_held(&self) -> bool {
+        !self.state.released.get()
+    }
+
+    /// Restores the previous value now. This is the same as dropping the guard.
+    pub fn release(self) {
+        drop(self);
+    }
+}
+
+impl Drop for OverrideGuard {
+    fn drop(&mut self) {
+        self.state.restore();
+        HELD.with_borrow_mut(|held| {
+            held.retain(|state| state.upgrade().is_some_and(|state| !state.released.get()));
+        });
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for OverrideGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverrideGuard")
+            .field("name", &self.state.name)
+            .field("index", &self.state.index())
+            .field("held", &self.is_held())
+            .finish()
+    }
+}
+
+impl OverrideState {
+    fn index(&self) -> Option<usize> {
+        match self.previous {
+            Previous::Element { index, .. } => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Writes the previous value back, unless that has already been done
+    fn restore(&self) {
+        if self.released.replace(true) {
+            return;
+        }
+        match &self.previous {
+            Previous::Scalar(value) => handle::<i32>(self.id).set(*value),
+            Previous::Array(values) => handle::<[i32]>(self.id).set(values),
+            Previous::Element { index, value } => handle::<[i32]>(self.id).set_at(*index, *value),
+        }
+    }
+}
+
+/// Finds the dataref `name`, checking that it can be written
+fn find_writable(name: &str) -> Result<XPLMDataRef, FindError> {
+    let name_c = CString::new(name)?;
+    let id = unsafe { XPLMFindDataRef(name_c.as_ptr()) };
+    if id.is_null() {
+        return Err(FindError::NotFound);
+    }
+    if unsafe { XPLMCanWriteDataRef(id) } != 1 {
+        return Err(FindError::NotWritable);
+    }
+    Ok(id)
+}
+
+/// Returns a handle to the dataref `id` as type `T`
+fn handle<T: ?Sized>(id: XPLMDataRef) -> DataRef<T, ReadWrite> {
+    DataRef {
+        id,
+        float_fallback: false,
+        _phantom: PhantomData,
+    }
+}
+
+/// Returns the overrides held by guards that have not been released, in the order they were
+/// taken.
+pub(super) fn held() -> Vec<HeldOverride> {
+    HELD.with_borrow(|held| {
+        held.iter()
+            .filter_map(Weak::upgrade)
+            .filter(|state| !state.released.get())
+            .map(|state| HeldOverride {
+                name: state.name.clone(),
+                index: state.index(),
+            })
+            .collect()
+    })
+}
+
+/// Releases every held override, most recently taken first. Called when the plugin is
+/// disabled.
+pub(crate) fn release_all() {
+    let held = HELD.with_borrow_mut(std::mem::take);
+    for state in held.iter().rev().filter_map(Weak::upgrade) {
+        state.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::c_void, ptr, ptr::NonNull};
+
+    use xplane_sys::XPLMDataTypeID;
+
+    use super::*;
+
+    #[test]
+    fn test_override_guard() {
+        let dataref_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().returning_st(move |name| {
+            let name = unsafe { std::ffi::CStr::from_ptr(name) };
+            if name.to_bytes() == b"sim/operation/override/override_joystick" {
+                dataref_ptr
+            } else {
+                ptr::null_mut()
+            }
+        });
+        let can_write_ctx = xplane_sys::XPLMCanWriteDataRef_context();
+        can_write_ctx.expect().returning_st(|_| 1);
+        let types_ctx = xplane_sys::XPLMGetDataRefTypes_context();
+        types_ctx.expect().returning_st(|_| XPLMDataTypeID::Int);
+        let value = Rc::new(Cell::new(0));
+        let value_1 = value.clone();
+        let get_ctx = xplane_sys::XPLMGetDatai_context();
+        get_ctx.expect().returning_st(move |_| value_1.get());
+        let value_2 = value.clone();
+        let set_ctx = xplane_sys::XPLMSetDatai_context();
+        set_ctx
+            .expect()
+            .times(2)
+            .returning_st(move |_, new_value| value_2.set(new_value));
+
+        assert!(matches!(
+            OverrideGuard::take("sim/operation/override/missing"),
+            Err(FindError::NotFound)
+        ));
+
+        let guard = OverrideGuard::take("sim/operation/override/override_joystick").unwrap();
+        assert_eq!(value.get(), 1);
+        assert!(guard.is_held());
+        assert_eq!(guard.index(), None);
+        let held = held();
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].name(), "sim/operation/override/override_joystick");
+
+        guard.release();
+        assert_eq!(value.get(), 0);
+        assert!(super::held().is_empty());
+    }
+
+    #[test]
+    fn test_release_all() {
+        let dataref_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().returning_st(move |_| dataref_ptr);
+        let can_write_ctx = xplane_sys::XPLMCanWriteDataRef_context();
+        can_write_ctx.expect().returning_st(|_| 1);
+        let types_ctx = xplane_sys::XPLMGetDataRefTypes_context();
+        types_ctx
+            .expect()
+            .returning_st(|_| XPLMDataTypeID::IntArray);
+        let values = Rc::new(RefCell::new([0, 0, 2, 0]));
+        let values_1 = values.clone();
+        let get_ctx = xplane_sys::XPLMGetDatavi_context();
+        get_ctx.expect().returning_st(move |_, out, offset, max| {
+            let values = values_1.borrow();
+            if out.is_null() {
+                return i32::try_from(values.len()).unwrap();
+            }
+            let offset = usize::try_from(offset).unwrap();
+            let count = usize::try_from(max).unwrap().min(values.len() - offset);
+            unsafe {
+                ptr::copy_nonoverlapping(values[offset..].as_ptr(), out, count);
+            }
+            i32::try_from(count).unwrap()
+        });
+        let values_2 = values.clone();
+        let set_ctx = xplane_sys::XPLMSetDatavi_context();
+        set_ctx
+            .expect()
+            .times(2)
+            .returning_st(move |_, input, offset, count| {
+                let offset = usize::try_from(offset).unwrap();
+                let count = usize::try_from(count).unwrap();
+                let input = unsafe { std::slice::from_raw_parts(input, count) };
+                values_2.borrow_mut()[offset..offset + count].copy_from_slice(input);
+            });
+
+        assert!(matches!(
+            OverrideGuard::take_element("sim/operation/override/override_throttles", 4),
+            Err(FindError::WrongLength {
+                expected: 5,
+                actual: 4
+            })
+        ));
+
+        let guard =
+            OverrideGuard::take_element("sim/operation/override/override_throttles", 1).unwrap();
+        assert_eq!(*values.borrow(), [0, 1, 2, 0]);
+        assert_eq!(held()[0].index(), Some(1));
+
+        release_all();
+        assert_eq!(*values.borrow(), [0, 0, 2, 0]);
+        assert!(!guard.is_held());
+        assert!(held().is_empty());
+        // Dropping a released guard does not write again
+        drop(guard);
+    }
+}
@@ -8,18 +8,27 @@ use std::{
     ffi::{CString, NulError},
     i32,
     marker::PhantomData,
-    ptr,
+    ptr, slice,
 };
 
+use num_enum::TryFromPrimitive;
 use snafu::prelude::*;
 
 use xplane_sys::{
-    XPLMDataRef, XPLMFindDataRef, XPLMGetDatab_f, XPLMGetDatad_f, XPLMGetDataf_f, XPLMGetDatai_f,
-    XPLMGetDatavf_f, XPLMGetDatavi_f, XPLMRegisterDataAccessor, XPLMSetDatab_f, XPLMSetDatad_f,
-    XPLMSetDataf_f, XPLMSetDatai_f, XPLMSetDatavf_f, XPLMSetDatavi_f, XPLMUnregisterDataAccessor,
+    XPLMDataRef, XPLMDataTypeID, XPLMFindDataRef, XPLMGetDatab_f, XPLMGetDatad_f, XPLMGetDataf_f,
+    XPLMGetDatai_f, XPLMGetDatavf_f, XPLMGetDatavi_f, XPLMRegisterDataAccessor, XPLMSetDatab_f,
+    XPLMSetDatad_f, XPLMSetDataf_f, XPLMSetDatai_f, XPLMSetDatavf_f, XPLMSetDatavi_f,
+    XPLMUnregisterDataAccessor,
 };
 
-use super::{Access, ArrayRead, ArrayReadWrite, DataRead, DataReadWrite, DataType, ReadOnly};
+use super::{
+    editor,
+    enums::{self, IntEnum, InvalidValue},
+    has_data_type, str_bytes,
+    units::{Degrees, Feet, Knots, Meters, MetersPerSecond, Radians},
+    Access, ArrayRead, ArrayReadWrite, DataRead, DataReadWrite, DataType, EnumRead, EnumReadWrite,
+    ReadOnly, ScalarType, StrRead, StrReadWrite,
+};
 
 /// A dataref owned by this plugin
 ///
@@ -33,10 +42,46 @@ pub struct OwnedData<T: DataType + ?Sized, A = ReadOnly> {
     /// This comes from a Box, so that it will have a constant memory location that is
     /// provided as a refcon to the callbacks.
     value: *mut T::Storage,
+    /// What to do when this plugin writes past the end of an array
+    overflow: OverflowPolicy,
     /// Data access and type phantom data.
     _phantom: PhantomData<(A, T)>,
 }
 
+/// What an owned array dataref does when this plugin writes past its current length
+///
+/// This only applies to writes made by this plugin. X-Plane and other plugins cannot change
+/// the length of an owned dataref, so their writes are always truncated to the current length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Values past the end of the array are discarded
+    #[default]
+    Truncate,
+    /// The array is extended to fit the values. Any gap between the old length and the offset
+    /// of the write is filled with zero values.
+    Grow,
+    /// The write is rejected, and the array is not changed
+    Error,
+}
+
+/// Inline storage for a fixed-length owned array dataref
+///
+/// The length comes first, so that the array callbacks can find it without knowing `N`.
+#[doc(hidden)]
+#[repr(C)]
+pub struct FixedArray<T, const N: usize> {
+    /// The number of elements in `values`, which is always `N`
+    len: usize,
+    /// The values
+    values: [T; N],
+}
+
+impl<T, const N: usize> FixedArray<T, N> {
+    pub(super) fn new(values: [T; N]) -> Self {
+        FixedArray { len: N, values }
+    }
+}
+
 impl<T: DataType + ?Sized, A: Access> OwnedData<T, A> {
     pub(super) fn new_with_value<S: AsRef<str>>(name: S, value: &T) -> Result<Self, CreateError> {
         let name = name.as_ref();
@@ -72,9 +117,11 @@ impl<T: DataType + ?Sized, A: Access> OwnedData<T, A> {
         };
 
         assert!(!id.is_null(), "Dataref ID of created dataref is null!");
+        editor::announce(&name_c);
         Ok(OwnedData {
             id,
             value,
+            overflow: OverflowPolicy::default(),
             _phantom: PhantomData,
         })
     }
@@ -127,42 +174,66 @@ impl<T: DataType + ?Sized, A: Access> OwnedData<T, A> {
     }
     fn int_array_read() -> XPLMGetDatavi_f {
         if T::sim_type().int_array() {
-            Some(array_read::<i32>)
+            if T::fixed_len().is_some() {
+                Some(fixed_array_read::<i32>)
+            } else {
+                Some(array_read::<i32>)
+            }
         } else {
             None
         }
     }
     fn int_array_write() -> XPLMSetDatavi_f {
         if T::sim_type().int_array() && A::writeable() {
-            Some(array_write::<i32>)
+            if T::fixed_len().is_some() {
+                Some(fixed_array_write::<i32>)
+            } else {
+                Some(array_write::<i32>)
+            }
         } else {
             None
         }
     }
     fn float_array_read() -> XPLMGetDatavf_f {
         if T::sim_type().float_array() {
-            Some(array_read::<f32>)
+            if T::fixed_len().is_some() {
+                Some(fixed_array_read::<f32>)
+            } else {
+                Some(array_read::<f32>)
+            }
         } else {
             None
         }
     }
     fn float_array_write() -> XPLMSetDatavf_f {
         if T::sim_type().float_array() && A::writeable() {
-            Some(array_write::<f32>)
+            if T::fixed_len().is_some() {
+                Some(fixed_array_write::<f32>)
+            } else {
+                Some(array_write::<f32>)
+            }
         } else {
             None
         }
     }
     fn byte_array_read() -> XPLMGetDatab_f {
-        if T::sim_type().data() {
-            Some(byte_array_read)
+        if has_data_type(T::sim_type()) {
+            if T::fixed_len().is_some() {
+                Some(fixed_byte_array_read)
+            } else {
+                Some(byte_array_read)
+            }
         } else {
             None
         }
     }
     fn byte_array_write() -> XPLMSetDatab_f {
-        if T::sim_type().data() && A::writeable() {
-            Some(byte_array_write)
+        if has_data_type(T::sim_type()) && A::writeable() {
+            if T::fixed_len().is_some() {
+                Some(fixed_byte_array_write)
+            } else {
+                Some(byte_array_write)
+            }
         } else {
             None
         }
@@ -175,6 +246,57 @@ impl<T: DataType + ?Sized, A: Access> OwnedData<T, A> {
     }
 }
 
+impl<T: ScalarType, A: Access> OwnedData<T, A> {
+    /// Creates a dataref that can be read and written as an int, a float, and a double.
+    /// Values are converted to and from `T` in the same way as `as` casts.
+    pub(super) fn new_multi<S: AsRef<str>>(name: S, value: T) -> Result<Self, CreateError> {
+        let name = name.as_ref();
+        let name_c = CString::new(name)?;
+
+        let existing = unsafe { XPLMFindDataRef(name_c.as_ptr()) };
+        if !existing.is_null() {
+            return Err(CreateError::Exists);
+        }
+
+        let value = Box::into_raw(Box::new(value));
+        let writeable = A::writeable();
+        let int_write: XPLMSetDatai_f = Some(write_as_int::<T>);
+        let float_write: XPLMSetDataf_f = Some(write_as_float::<T>);
+        let double_write: XPLMSetDatad_f = Some(write_as_double::<T>);
+
+        let id = unsafe {
+            XPLMRegisterDataAccessor(
+                name_c.as_ptr(),
+                XPLMDataTypeID::Int | XPLMDataTypeID::Float | XPLMDataTypeID::Double,
+                Self::writeable(),
+                Some(read_as_int::<T>),
+                int_write.filter(|_| writeable),
+                Some(read_as_float::<T>),
+                float_write.filter(|_| writeable),
+                Some(read_as_double::<T>),
+                double_write.filter(|_| writeable),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                value.cast(),
+                value.cast(),
+            )
+        };
+
+        assert!(!id.is_null(), "Dataref ID of created dataref is null!");
+        editor::announce(&name_c);
+        Ok(OwnedData {
+            id,
+            value,
+            overflow: OverflowPolicy::default(),
+            _phantom: PhantomData,
+        })
+    }
+}
+
 impl<T: DataType + ?Sized, A> Drop for OwnedData<T, A> {
     fn drop(&mut self) {
         unsafe { XPLMUnregisterDataAccessor(self.id) }
@@ -183,12 +305,29 @@ impl<T: DataType + ?Sized, A> Drop for OwnedData<T, A> {
 
 // DataRead and DataReadWrite
 macro_rules! impl_read_write {
+    ([$native_type:ty; N]) => {
+        impl<const N: usize, A: Access> DataRead<[$native_type; N]>
+            for OwnedData<[$native_type; N], A>
+        {
+            fn get(&self) -> [$native_type; N] {
+                self.value_ref().values
+            }
+        }
+        impl<const N: usize, A: Access> DataReadWrite<[$native_type; N]>
+            for OwnedData<[$native_type; N], A>
+        {
+            fn set(&mut self, value: [$native_type; N]) {
+                self.value_mut().values = value;
+            }
+        }
+    };
     ([$native_type:ty]) => {
         impl<A: Access> ArrayRead<[$native_type]> for OwnedData<[$native_type], A> {
-            fn get(&self, dest: &mut [$native_type]) -> usize {
-                let copy_length = cmp::min(dest.len(), self.value_ref().len());
+            fn get_range(&self, offset: usize, dest: &mut [$native_type]) -> usize {
+                let value = self.value_ref().get(offset..).unwrap_or_default();
+                let copy_length = cmp::min(dest.len(), value.len());
                 let dest_sub = &mut dest[..copy_length];
-                let value_sub = &self.value_ref()[..copy_length];
+                let value_sub = &value[..copy_length];
                 dest_sub.copy_from_slice(value_sub);
                 copy_length
             }
@@ -197,11 +336,80 @@ macro_rules! impl_read_write {
             }
         }
         impl<A: Access> ArrayReadWrite<[$native_type]> for OwnedData<[$native_type], A> {
-            fn set(&mut self, values: &[$native_type]) {
-                let copy_length = cmp::min(values.len(), self.value_ref().len());
+            /// Writes values starting at `offset`, following this dataref's [`OverflowPolicy`].
+            ///
+            /// With [`OverflowPolicy::Error`], a write that does not fit is ignored.
+            /// Use [`OwnedData::try_set_range`] to find out whether it was.
+            fn set_range(&mut self, offset: usize, values: &[$native_type]) {
+                let _ = self.try_set_range(offset, values);
+            }
+        }
+        impl<A: Access> OwnedData<[$native_type], A> {
+            /// Changes the length of this dataref.
+            ///
+            /// New elements are set to zero. X-Plane and other plugins will see the new length
+            /// the next time they read this dataref.
+            pub fn set_len(&mut self, len: usize) {
+                self.resize(len, <$native_type>::default());
+            }
+            /// Changes the length of this dataref, filling any new elements with `value`.
+            ///
+            /// X-Plane and other plugins will see the new length the next time they read this
+            /// dataref.
+            pub fn resize(&mut self, len: usize, value: $native_type) {
+                self.value_mut().resize(len, value);
+            }
+            /// Returns the policy for writes past the end of this dataref
+            pub fn overflow_policy(&self) -> OverflowPolicy {
+                self.overflow
+            }
+            /// Sets the policy for writes past the end of this dataref
+            pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+                self.overflow = policy;
+            }
+            /// Replaces the whole contents of this dataref, following its [`OverflowPolicy`].
+            ///
+            /// # Errors
+            /// Returns an error if the policy is [`OverflowPolicy::Error`] and `values` is
+            /// longer than this dataref. The dataref is not changed.
+            pub fn try_set(&mut self, values: &[$native_type]) -> Result<(), OverflowError> {
+                self.try_set_range(0, values)
+            }
+            /// Writes values starting at `offset`, following this dataref's [`OverflowPolicy`].
+            ///
+            /// # Errors
+            /// Returns an error if the policy is [`OverflowPolicy::Error`] and the values do
+            /// not fit in this dataref. The dataref is not changed.
+            pub fn try_set_range(
+                &mut self,
+                offset: usize,
+                values: &[$native_type],
+            ) -> Result<(), OverflowError> {
+                let len = self.value_ref().len();
+                let end = offset.saturating_add(values.len());
+                if end > len {
+                    match self.overflow {
+                        OverflowPolicy::Truncate => {}
+                        OverflowPolicy::Grow => {
+                            self.value_mut().resize(end, <$native_type>::default())
+                        }
+                        OverflowPolicy::Error => {
+                            return Err(OverflowError {
+                                offset,
+                                count: values.len(),
+                                len,
+                            })
+                        }
+                    }
+                }
+                let Some(value) = self.value_mut().get_mut(offset..) else {
+                    return Ok(());
+                };
+                let copy_length = cmp::min(values.len(), value.len());
                 let src_sub = &values[..copy_length];
-                let values_sub = &mut self.value_mut()[..copy_length];
+                let values_sub = &mut value[..copy_length];
                 values_sub.copy_from_slice(src_sub);
+                Ok(())
             }
         }
     };
@@ -235,6 +443,92 @@ impl_read_write!([u32]);
 impl_read_write!([f32]);
 impl_read_write!([u8]);
 impl_read_write!([i8]);
+impl_read_write!([i32; N]);
+impl_read_write!([f32; N]);
+impl_read_write!([u8; N]);
+impl_read_write!(Meters<f32>);
+impl_read_write!(Meters<f64>);
+impl_read_write!(Feet<f32>);
+impl_read_write!(Feet<f64>);
+impl_read_write!(Knots<f32>);
+impl_read_write!(Knots<f64>);
+impl_read_write!(MetersPerSecond<f32>);
+impl_read_write!(MetersPerSecond<f64>);
+impl_read_write!(Degrees<f32>);
+impl_read_write!(Degrees<f64>);
+impl_read_write!(Radians<f32>);
+impl_read_write!(Radians<f64>);
+
+impl<A: Access> StrRead for OwnedData<str, A> {
+    fn get_bytes(&self) -> Vec<u8> {
+        self.value_ref().clone()
+    }
+    fn max_len(&self) -> usize {
+        self.value_ref().len()
+    }
+}
+
+impl<A: Access> StrReadWrite for OwnedData<str, A> {
+    fn set_with_policy(
+        &mut self,
+        value: &str,
+        policy: OverflowPolicy,
+    ) -> Result<(), OverflowError> {
+        *self.value_mut() = str_bytes(value, self.value_ref().len(), policy)?;
+        Ok(())
+    }
+    /// Writes a string following this dataref's [`OverflowPolicy`], and fills the rest of the
+    /// dataref with NUL bytes.
+    ///
+    /// With [`OverflowPolicy::Error`], a string that does not fit is ignored.
+    fn set(&mut self, value: &str) {
+        let _ = self.set_with_policy(value, self.overflow);
+    }
+}
+
+impl<A: Access> OwnedData<str, A> {
+    /// Changes the length of this dataref in bytes, which is the longest string it can hold.
+    ///
+    /// New bytes are set to NUL. X-Plane and other plugins will see the new length the next
+    /// time they read this dataref.
+    pub fn set_max_len(&mut self, len: usize) {
+        self.value_mut().resize(len, 0);
+    }
+    /// Returns the policy for strings that are longer than this dataref
+    #[must_use]
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow
+    }
+    /// Sets the policy for strings that are longer than this dataref
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow = policy;
+    }
+}
+
+impl<E, A: Access> EnumRead<E> for OwnedData<IntEnum<E>, A>
+where
+    E: TryFromPrimitive + Into<E::Primitive> + Copy,
+    E::Primitive: ScalarType,
+{
+    fn get(&self) -> Result<E, InvalidValue> {
+        enums::from_raw(self.get_raw())
+    }
+    fn get_raw(&self) -> i32 {
+        unsafe { *self.value }
+    }
+}
+
+impl<E, A: Access> EnumReadWrite<E> for OwnedData<IntEnum<E>, A>
+where
+    E: TryFromPrimitive + Into<E::Primitive> + Copy,
+    E::Primitive: ScalarType,
+{
+    fn set(&mut self, value: E) {
+        unsafe {
+            *self.value = enums::to_raw(value);
+        }
+    }
+}
 
 /// Errors that can occur when creating a `DataRef`
 #[derive(Snafu, Debug)]
@@ -252,6 +546,18 @@ pub enum CreateError {
     Exists,
 }
 
+/// A write to an owned array dataref did not fit, and its policy is [`OverflowPolicy::Error`]
+#[derive(Snafu, Debug)]
+#[snafu(display("Writing {count} values at offset {offset} overflows array of length {len}"))]
+pub struct OverflowError {
+    /// The offset of the write
+    pub(super) offset: usize,
+    /// The number of values written
+    pub(super) count: usize,
+    /// The length of the array
+    pub(super) len: usize,
+}
+
 // Read/write callbacks
 // The refcon is a pointer to the data
 
@@ -269,6 +575,42 @@ unsafe extern "C-unwind" fn write_single<T: DataType + Copy>(refcon: *mut c_void
     }
 }
 
+/// Int read callback for a multi-typed dataref
+unsafe extern "C-unwind" fn read_as_int<T: ScalarType>(refcon: *mut c_void) -> c_int {
+    unsafe { *refcon.cast::<T>() }.to_int()
+}
+
+/// Int write callback for a multi-typed dataref
+unsafe extern "C-unwind" fn write_as_int<T: ScalarType>(refcon: *mut c_void, value: c_int) {
+    unsafe {
+        *refcon.cast::<T>() = T::from_int(value);
+    }
+}
+
+/// Float read callback for a multi-typed dataref
+unsafe extern "C-unwind" fn read_as_float<T: ScalarType>(refcon: *mut c_void) -> f32 {
+    unsafe { *refcon.cast::<T>() }.to_float()
+}
+
+/// Float write callback for a multi-typed dataref
+unsafe extern "C-unwind" fn write_as_float<T: ScalarType>(refcon: *mut c_void, value: f32) {
+    unsafe {
+        *refcon.cast::<T>() = T::from_float(value);
+    }
+}
+
+/// Double read callback for a multi-typed dataref
+unsafe extern "C-unwind" fn read_as_double<T: ScalarType>(refcon: *mut c_void) -> f64 {
+    unsafe { *refcon.cast::<T>() }.to_double()
+}
+
+/// Double write callback for a multi-typed dataref
+unsafe extern "C-unwind" fn write_as_double<T: ScalarType>(refcon: *mut c_void, value: f64) {
+    unsafe {
+        *refcon.cast::<T>() = T::from_double(value);
+    }
+}
+
 /// Byte array read callback
 unsafe extern "C-unwind" fn byte_array_read(
     refcon: *mut c_void,
@@ -291,33 +633,114 @@ unsafe extern "C-unwind" fn byte_array_write(
     }
 }
 
+/// Fixed-length byte array read callback
+unsafe extern "C-unwind" fn fixed_byte_array_read(
+    refcon: *mut c_void,
+    values: *mut c_void,
+    offset: c_int,
+    max: c_int,
+) -> c_int {
+    unsafe { fixed_array_read::<u8>(refcon, values.cast::<u8>(), offset, max) }
+}
+
+/// Fixed-length byte array write callback
+unsafe extern "C-unwind" fn fixed_byte_array_write(
+    refcon: *mut c_void,
+    values: *mut c_void,
+    offset: c_int,
+    max: c_int,
+) {
+    unsafe {
+        fixed_array_write::<u8>(refcon, values.cast::<u8>(), offset, max);
+    }
+}
+
 /// If values is null, returns the length of this dataref.
 /// Otherwise, reads up to max elements from this dataref starting at offset offset and copies them
 /// into values.
 #[inline]
-#[allow(
-    clippy::cast_sign_loss,
-    clippy::cast_possible_truncation,
-    clippy::cast_possible_wrap
-)]
 unsafe extern "C-unwind" fn array_read<T: Copy>(
     refcon: *mut c_void,
     values: *mut T,
     offset: c_int,
     max: c_int,
 ) -> c_int {
-    let offset = offset as usize;
-    let max = max as usize;
-    let dataref_content = refcon as *const Vec<T>;
-    let dataref_length = unsafe { (*dataref_content).len() };
+    let dataref_content = unsafe { &*(refcon as *const Vec<T>) };
+    unsafe { read_slice(dataref_content, values, offset, max) }
+}
+
+/// Reads up to max items from values and writes them to this dataref, starting at offset offset
+#[inline]
+unsafe extern "C-unwind" fn array_write<T: Copy>(
+    refcon: *mut c_void,
+    values: *mut T,
+    offset: c_int,
+    max: c_int,
+) {
+    let dataref_content = unsafe { &mut *refcon.cast::<Vec<T>>() };
+    unsafe {
+        write_slice(dataref_content, values, offset, max);
+    }
+}
+
+/// The same as [`array_read`], for a dataref stored in a [`FixedArray`]
+#[inline]
+unsafe extern "C-unwind" fn fixed_array_read<T: Copy>(
+    refcon: *mut c_void,
+    values: *mut T,
+    offset: c_int,
+    max: c_int,
+) -> c_int {
+    let dataref_content = unsafe { fixed_values::<T>(refcon) };
+    unsafe { read_slice(dataref_content, values, offset, max) }
+}
+
+/// The same as [`array_write`], for a dataref stored in a [`FixedArray`]
+#[inline]
+unsafe extern "C-unwind" fn fixed_array_write<T: Copy>(
+    refcon: *mut c_void,
+    values: *mut T,
+    offset: c_int,
+    max: c_int,
+) {
+    let dataref_content = unsafe { fixed_values::<T>(refcon) };
+    unsafe {
+        write_slice(dataref_content, values, offset, max);
+    }
+}
+
+/// Returns the values in a [`FixedArray`] of any length.
+///
+/// # Safety
+/// refcon must point to a `FixedArray<T, N>`, and the returned slice must not outlive it.
+unsafe fn fixed_values<'a, T>(refcon: *mut c_void) -> &'a mut [T] {
+    // The layout of values does not depend on N, so this is the same for any N
+    let array = refcon.cast::<FixedArray<T, 0>>();
+    let len = unsafe { (*array).len };
+    let values = unsafe { ptr::addr_of_mut!((*array).values) }.cast::<T>();
+    unsafe { slice::from_raw_parts_mut(values, len) }
+}
+
+/// Implements an array read callback on top of `dataref_content`
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+unsafe fn read_slice<T: Copy>(
+    dataref_content: &[T],
+    values: *mut T,
+    offset: c_int,
+    max: c_int,
+) -> c_int {
+    let dataref_length = dataref_content.len();
     if values.is_null() {
         dataref_length as c_int
     } else {
+        let (Ok(offset), Ok(max)) = (usize::try_from(offset), usize::try_from(max)) else {
+            return 0;
+        };
         // Check that offset is within dataref content
         if offset >= dataref_length {
             return 0;
         }
-        let dataref_offset = unsafe { (*dataref_content).as_ptr().add(offset) };
+        let dataref_offset = unsafe { dataref_content.as_ptr().add(offset) };
         let copy_length = cmp::min(max, dataref_length - offset);
         unsafe {
             ptr::copy_nonoverlapping(dataref_offset, values, copy_length);
@@ -326,26 +749,276 @@ unsafe extern "C-unwind" fn array_read<T: Copy>(
     }
 }
 
-/// Reads up to max items from values and writes them to this dataref, starting at offset offset
-#[inline]
-#[allow(clippy::cast_sign_loss)]
-unsafe extern "C-unwind" fn array_write<T: Copy>(
-    refcon: *mut c_void,
+/// Implements an array write callback on top of `dataref_content`
+unsafe fn write_slice<T: Copy>(
+    dataref_content: &mut [T],
     values: *mut T,
     offset: c_int,
     max: c_int,
 ) {
-    let offset = offset as usize;
-    let max = max as usize;
-    let dataref_content = refcon.cast::<Vec<T>>();
-    let dataref_length = unsafe { (*dataref_content).len() };
+    let (Ok(offset), Ok(max)) = (usize::try_from(offset), usize::try_from(max)) else {
+        return;
+    };
+    let dataref_length = dataref_content.len();
 
     if offset >= dataref_length {
         return;
     }
-    let dataref_offset = unsafe { (*dataref_content).as_mut_ptr().add(offset) };
+    let dataref_offset = unsafe { dataref_content.as_mut_ptr().add(offset) };
     let copy_length = cmp::min(max, dataref_length - offset);
     unsafe {
         ptr::copy_nonoverlapping(values, dataref_offset, copy_length);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, ptr::NonNull, rc::Rc};
+
+    use super::*;
+    use crate::data::ReadWrite;
+
+    #[test]
+    fn test_resize_and_overflow() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().once().return_once_st(|_| ptr::null_mut());
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx
+            .expect()
+            .once()
+            .return_once_st(move |_, _, _, _, _, _, _, _, _, _, _, _, _, _, _, _, _| expected_ptr);
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, expected_ptr));
+
+        let mut dataref =
+            OwnedData::<[i32], ReadWrite>::new_with_value("xplane_rs/test/array", &[1, 2][..])
+                .unwrap();
+        let reported_len =
+            unsafe { array_read::<i32>(dataref.value.cast(), ptr::null_mut(), 0, 0) };
+        assert_eq!(reported_len, 2);
+
+        dataref.set(&[3, 4, 5]);
+        assert_eq!(dataref.as_vec(), vec![3, 4]);
+
+        dataref.set_overflow_policy(OverflowPolicy::Error);
+        assert!(dataref.try_set_range(1, &[6, 7]).is_err());
+        assert_eq!(dataref.as_vec(), vec![3, 4]);
+
+        dataref.set_overflow_policy(OverflowPolicy::Grow);
+        dataref.set_range(3, &[8]);
+        assert_eq!(dataref.as_vec(), vec![3, 4, 0, 8]);
+
+        dataref.set_len(1);
+        assert_eq!(dataref.as_vec(), vec![3]);
+        let reported_len =
+            unsafe { array_read::<i32>(dataref.value.cast(), ptr::null_mut(), 0, 0) };
+        assert_eq!(reported_len, 1);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_multi_typed() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().once().return_once_st(|_| ptr::null_mut());
+        let callbacks = Rc::new(RefCell::new(None));
+        let callbacks_1 = callbacks.clone();
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx.expect().once().return_once_st(
+            move |_, type_, _, ir, iw, fr, fw, dr, dw, _, _, _, _, _, _, refcon, _| {
+                assert!(type_.int() && type_.float() && type_.double());
+                *callbacks_1.borrow_mut() = Some((
+                    ir.unwrap(),
+                    iw.unwrap(),
+                    fr.unwrap(),
+                    fw.unwrap(),
+                    dr.unwrap(),
+                    dw.unwrap(),
+                    refcon,
+                ));
+                expected_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, expected_ptr));
+
+        let mut dataref =
+            OwnedData::<f32, ReadWrite>::new_multi("xplane_rs/test/multi", 2.75).unwrap();
+        let (ir, iw, fr, fw, dr, dw, refcon) = callbacks.borrow_mut().take().unwrap();
+        unsafe {
+            assert_eq!(ir(refcon), 2);
+            assert_eq!(fr(refcon), 2.75);
+            assert_eq!(dr(refcon), 2.75);
+            iw(refcon, 7);
+        }
+        assert_eq!(dataref.get(), 7.0);
+        unsafe {
+            fw(refcon, 1.5);
+            assert_eq!(ir(refcon), 1);
+            dw(refcon, -3.25);
+        }
+        assert_eq!(dataref.get(), -3.25);
+        dataref.set(9.5);
+        assert_eq!(unsafe { ir(refcon) }, 9);
+    }
+
+    #[test]
+    fn test_enum() {
+        use num_enum::IntoPrimitive;
+
+        use crate::data::{enums::IntEnum, EnumRead, EnumReadWrite};
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+        #[repr(i32)]
+        enum GearHandle {
+            Up = 0,
+            Down = 1,
+        }
+
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().once().return_once_st(|_| ptr::null_mut());
+        let callbacks = Rc::new(RefCell::new(None));
+        let callbacks_1 = callbacks.clone();
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx.expect().once().return_once_st(
+            move |_, type_, _, ir, iw, _, _, _, _, _, _, _, _, _, _, refcon, _| {
+                assert_eq!(type_, XPLMDataTypeID::Int);
+                *callbacks_1.borrow_mut() = Some((ir.unwrap(), iw.unwrap(), refcon));
+                expected_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, expected_ptr));
+
+        let mut dataref = OwnedData::<IntEnum<GearHandle>, ReadWrite>::new_with_value(
+            "xplane_rs/test/gear",
+            &IntEnum(GearHandle::Down),
+        )
+        .unwrap();
+        let (ir, iw, refcon) = callbacks.borrow_mut().take().unwrap();
+        assert_eq!(unsafe { ir(refcon) }, 1);
+        assert_eq!(dataref.get(), Ok(GearHandle::Down));
+        dataref.set(GearHandle::Up);
+        assert_eq!(unsafe { ir(refcon) }, 0);
+        unsafe {
+            iw(refcon, 5);
+        }
+        assert_eq!(dataref.get_raw(), 5);
+        assert_eq!(dataref.get().unwrap_err().value(), 5);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_fixed_array() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().once().return_once_st(|_| ptr::null_mut());
+        let callbacks = Rc::new(RefCell::new(None));
+        let callbacks_1 = callbacks.clone();
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx.expect().once().return_once_st(
+            move |_, type_, _, _, _, _, _, _, _, _, _, far, faw, _, _, refcon, _| {
+                assert_eq!(type_, XPLMDataTypeID::FloatArray);
+                *callbacks_1.borrow_mut() = Some((far.unwrap(), faw.unwrap(), refcon));
+                expected_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, expected_ptr));
+
+        let mut dataref = OwnedData::<[f32; 3], ReadWrite>::new_with_value(
+            "xplane_rs/test/fixed",
+            &[1.0, 2.0, 3.0],
+        )
+        .unwrap();
+        let (far, faw, refcon) = callbacks.borrow_mut().take().unwrap();
+        let mut out = [0.0f32; 4];
+        unsafe {
+            assert_eq!(far(refcon, ptr::null_mut(), 0, 0), 3);
+            assert_eq!(far(refcon, out.as_mut_ptr(), 1, 4), 2);
+        }
+        assert_eq!(out, [2.0, 3.0, 0.0, 0.0]);
+        dataref.set([4.0, 5.0, 6.0]);
+        let mut incoming = [7.0f32, 8.0];
+        unsafe {
+            faw(refcon, incoming.as_mut_ptr(), 2, 2);
+        }
+        assert_eq!(dataref.get(), [4.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn test_string() {
+        use crate::data::{StrRead, StrReadWrite};
+
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().once().return_once_st(|_| ptr::null_mut());
+        let callbacks = Rc::new(RefCell::new(None));
+        let callbacks_1 = callbacks.clone();
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx.expect().once().return_once_st(
+            move |_, type_, _, _, _, _, _, _, _, _, _, _, _, br, bw, refcon, _| {
+                assert_eq!(type_, XPLMDataTypeID::Data);
+                *callbacks_1.borrow_mut() = Some((br.unwrap(), bw.unwrap(), refcon));
+                expected_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, expected_ptr));
+
+        let mut dataref =
+            OwnedData::<str, ReadWrite>::new_with_value("xplane_rs/test/tailnum", "N12345")
+                .unwrap();
+        let (br, bw, refcon) = callbacks.borrow_mut().take().unwrap();
+        assert_eq!(dataref.max_len(), 6);
+        dataref.set("D-EX");
+        assert_eq!(dataref.get_bytes(), b"D-EX\0\0");
+        assert_eq!(dataref.get(), "D-EX");
+
+        // Truncated at a character boundary
+        dataref.set("G-ÆØÅ");
+        assert_eq!(dataref.get_bytes(), b"G-\xc3\x86\xc3\x98");
+        dataref.set_overflow_policy(OverflowPolicy::Error);
+        assert!(dataref
+            .set_with_policy("TOO LONG", OverflowPolicy::Error)
+            .is_err());
+        dataref.set("TOO LONG");
+        assert_eq!(dataref.get(), "G-ÆØ");
+        dataref.set_overflow_policy(OverflowPolicy::Grow);
+        dataref.set("SPEEDBIRD 1");
+        assert_eq!(dataref.max_len(), 11);
+        let mut out = [0u8; 16];
+        unsafe {
+            assert_eq!(br(refcon, ptr::null_mut(), 0, 0), 11);
+            assert_eq!(br(refcon, out.as_mut_ptr().cast(), 0, 16), 11);
+        }
+        assert_eq!(&out[..11], b"SPEEDBIRD 1");
+
+        // Invalid UTF-8 written by another plugin
+        let mut incoming = *b"AB\xffC";
+        unsafe {
+            bw(refcon, incoming.as_mut_ptr().cast(), 0, 4);
+        }
+        assert_eq!(dataref.get(), "AB\u{fffd}CDBIRD 1");
+        assert!(dataref.get_strict().is_err());
+        dataref.set_max_len(2);
+        assert_eq!(dataref.get_strict().unwrap(), "AB");
+    }
+}
@@ -0,0 +1,613 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    cell::RefCell,
+    ffi::{c_int, c_void, CString},
+    fmt,
+    marker::PhantomData,
+    rc::{Rc, Weak},
+};
+
+use xplane_sys::{
+    XPLMCanWriteDataRef, XPLMDataRef, XPLMDataTypeID, XPLMFindDataRef, XPLMGetDataRefTypes,
+    XPLMRegisterDataAccessor, XPLMSetDatad_f, XPLMSetDataf_f, XPLMSetDatai_f,
+    XPLMUnregisterDataAccessor,
+};
+
+use crate::message::MessageId;
+
+use super::{
+    borrowed::DataRef, editor, has_data_type, owned::CreateError, Access, ArrayRead,
+    ArrayReadWrite, DataRead, DataReadWrite, ReadOnly, ReadWrite, ScalarType,
+};
+
+thread_local! {
+    /// Proxies whose targets have not been found yet
+    static PENDING: RefCell<Vec<Weak<RefCell<ProxyInner>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A step that changes a value on its way from the target dataref to the proxy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Multiplies the value by a factor
+    Scale(f64),
+    /// Adds an amount to the value
+    Offset(f64),
+    /// Limits the value to a range
+    Clamp {
+        /// The smallest value
+        min: f64,
+        /// The largest value
+        max: f64,
+    },
+    /// Turns zero into one, and any other value into zero
+    Invert,
+}
+
+impl Transform {
+    /// Applies this transform to a value read from the target
+    fn apply(self, value: f64) -> f64 {
+        match self {
+            Transform::Scale(factor) => value * factor,
+            Transform::Offset(amount) => value + amount,
+            // Unlike f64::clamp, this does not panic if min is more than max or either is NaN.
+            Transform::Clamp { min, max } => value.max(min).min(max),
+            Transform::Invert => f64::from(u8::from(value == 0.0)),
+        }
+    }
+
+    /// Reverses this transform for a value written to the proxy
+    ///
+    /// A clamp is not reversible, so the written value is clamped instead. Returns `None` for a
+    /// scale by zero, which cannot be reversed either.
+    fn reverse(self, value: f64) -> Option<f64> {
+        match self {
+            Transform::Scale(0.0) => None,
+            Transform::Scale(factor) => Some(value / factor),
+            Transform::Offset(amount) => Some(value - amount),
+            Transform::Clamp { .. } | Transform::Invert => Some(self.apply(value)),
+        }
+    }
+}
+
+/// The dataref that a [`ProxyData`] forwards to, and the transforms applied on the way
+///
+/// Transforms are applied to reads in the order they were added, and reversed in the opposite
+/// order for writes.
+///
+/// ```no_run
+/// use xplane::data::{proxy::ProxyTarget, DataApi, ReadOnly};
+///
+/// fn publish(data: &mut DataApi) {
+///     let target = ProxyTarget::new("sim/flightmodel/position/elevation")
+///         .scale(1.0 / 0.3048)
+///         .clamp(0.0, 60_000.0);
+///     let proxy = data
+///         .new_proxy::<ReadOnly, _>("myplugin/cockpit/altitude_ft", target)
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyTarget {
+    /// The name of the target dataref
+    name: String,
+    /// The element to use, if the target is an array
+    index: Option<usize>,
+    /// The transforms, in the order they are applied to reads
+    transforms: Vec<Transform>,
+}
+
+impl ProxyTarget {
+    /// Creates a target for the single-value dataref called `name`, with no transforms
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        ProxyTarget {
+            name: name.into(),
+            index: None,
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Creates a target for one element of the array dataref called `name`, with no transforms
+    pub fn element<S: Into<String>>(name: S, index: usize) -> Self {
+        ProxyTarget {
+            index: Some(index),
+            ..Self::new(name)
+        }
+    }
+
+    /// Adds a transform
+    #[must_use]
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Multiplies the value by `factor`
+    #[must_use]
+    pub fn scale(self, factor: f64) -> Self {
+        self.transform(Transform::Scale(factor))
+    }
+
+    /// Adds `amount` to the value
+    #[must_use]
+    pub fn offset(self, amount: f64) -> Self {
+        self.transform(Transform::Offset(amount))
+    }
+
+    /// Limits the value to the range from `min` to `max`
+    ///
+    /// If `min` is more than `max`, they are swapped.
+    #[must_use]
+    pub fn clamp(self, min: f64, max: f64) -> Self {
+        self.transform(Transform::Clamp {
+            min: min.min(max),
+            max: min.max(max),
+        })
+    }
+
+    /// Turns zero into one, and any other value into zero
+    #[must_use]
+    pub fn invert(self) -> Self {
+        self.transform(Transform::Invert)
+    }
+
+    /// Returns the name of the target dataref
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the element of the target that is used, if it is an array
+    #[must_use]
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Returns the transforms, in the order they are applied to reads
+    #[must_use]
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    /// Applies the transforms to a value read from the target
+    fn apply(&self, value: f64) -> f64 {
+        self.transforms
+            .iter()
+            .fold(value, |value, transform| transform.apply(value))
+    }
+
+    /// Reverses the transforms for a value written to the proxy
+    ///
+    /// Returns `None` if the transforms cannot be reversed, or the result is not finite.
+    fn reverse(&self, value: f64) -> Option<f64> {
+        self.transforms
+            .iter()
+            .rev()
+            .try_fold(value, |value, transform| transform.reverse(value))
+            .filter(|value| value.is_finite())
+    }
+}
+
+/// A dataref owned by this plugin that forwards to another dataref
+///
+/// X-Plane and other plugins can read the proxy as an int, a float or a double. If the
+/// access parameter is [`ReadWrite`], they can also write it, and the value is written to the
+/// target if the target is writable. This lets other plugins and cockpits bind to a stable
+/// name while the target changes underneath.
+///
+/// The target does not need to exist when the proxy is created. Until it is found, the proxy
+/// reads as zero and ignores writes. Proxies look for missing targets again whenever
+/// [`MessageId::DatarefsAdded`] is received (which requires the
+/// `XPLM_WANTS_DATAREF_NOTIFICATIONS` feature) and whenever the user's plane is loaded.
+pub struct ProxyData<A = ReadOnly> {
+    /// The dataref handle
+    id: XPLMDataRef,
+    /// The target and its resolved handle. The callbacks get a pointer to this.
+    inner: Rc<RefCell<ProxyInner>>,
+    /// Data access phantom data
+    _phantom: PhantomData<(*mut (), A)>,
+}
+
+struct ProxyInner {
+    target: ProxyTarget,
+    /// The target name, for finding it
+    target_c: CString,
+    /// The target handle, once it has been found
+    resolved: Option<Resolved>,
+}
+
+/// A found target dataref
+struct Resolved {
+    id: XPLMDataRef,
+    types: XPLMDataTypeID,
+    writable: bool,
+}
+
+impl<A: Access> ProxyData<A> {
+    pub(super) fn new<S: AsRef<str>>(name: S, target: ProxyTarget) -> Result<Self, CreateError> {
+        let name_c = CString::new(name.as_ref())?;
+        let target_c = CString::new(target.name.as_str())?;
+
+        let existing = unsafe { XPLMFindDataRef(name_c.as_ptr()) };
+        if !existing.is_null() {
+            return Err(CreateError::Exists);
+        }
+
+        let inner = Rc::new(RefCell::new(ProxyInner {
+            target,
+            target_c,
+            resolved: None,
+        }));
+        if !inner.borrow_mut().resolve() {
+            PENDING.with_borrow_mut(|pending| pending.push(Rc::downgrade(&inner)));
+        }
+
+        let refcon = Rc::as_ptr(&inner).cast_mut().cast::<c_void>();
+        let writeable = A::writeable();
+        let int_write: XPLMSetDatai_f = Some(write_int);
+        let float_write: XPLMSetDataf_f = Some(write_float);
+        let double_write: XPLMSetDatad_f = Some(write_double);
+        let id = unsafe {
+            XPLMRegisterDataAccessor(
+                name_c.as_ptr(),
+                XPLMDataTypeID::Int | XPLMDataTypeID::Float | XPLMDataTypeID::Double,
+                i32::from(writeable),
+                Some(read_int),
+                int_write.filter(|_| writeable),
+                Some(read_float),
+                float_write.filter(|_| writeable),
+                Some(read_double),
+                double_write.filter(|_| writeable),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                refcon,
+                refcon,
+            )
+        };
+
+        assert!(!id.is_null(), "Dataref ID of created dataref is null!");
+        editor::announce(&name_c);
+        Ok(ProxyData {
+            id,
+            inner,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<A> ProxyData<A> {
+    /// Returns true if the target dataref has been found
+    #[must_use]
+    pub fn is_resolved(&self) -> bool {
+        self.inner.borrow().resolved.is_some()
+    }
+
+    /// Looks for the target dataref again if it has not been found yet.
+    ///
+    /// This normally happens automatically; see the [module documentation](self).
+    ///
+    /// Returns true if the target has been found.
+    pub fn retry(&mut self) -> bool {
+        self.inner.borrow_mut().resolve()
+    }
+
+    /// Returns the target of this proxy
+    #[must_use]
+    pub fn target(&self) -> ProxyTarget {
+        self.inner.borrow().target.clone()
+    }
+}
+
+impl<A> DataRead<f64> for ProxyData<A> {
+    /// Reads the transformed value of the target, or zero if it has not been found
+    fn get(&self) -> f64 {
+        self.inner.borrow().read()
+    }
+}
+
+impl<A> DataReadWrite<f64> for ProxyData<A> {
+    /// Writes a value to the target, reversing the transforms.
+    /// Nothing is written if the target has not been found or is not writable.
+    fn set(&mut self, value: f64) {
+        self.inner.borrow().write(value);
+    }
+}
+
+impl<A> Drop for ProxyData<A> {
+    fn drop(&mut self) {
+        unsafe { XPLMUnregisterDataAccessor(self.id) }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<A> fmt::Debug for ProxyData<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyData")
+            .field("id", &"[dataref handle]")
+            .field("target", &self.inner.borrow().target)
+            .field("resolved", &self.is_resolved())
+            .finish()
+    }
+}
+
+impl ProxyInner {
+    /// Finds the target dataref if it has not been found yet. Returns true if it has been found.
+    ///
+    /// A target that exists but has no type matching the proxy target is treated as missing.
+    fn resolve(&mut self) -> bool {
+        if self.resolved.is_some() {
+            return true;
+        }
+        let id = unsafe { XPLMFindDataRef(self.target_c.as_ptr()) };
+        if id.is_null() {
+            return false;
+        }
+        let types = unsafe { XPLMGetDataRefTypes(id) };
+        let usable = if self.target.index.is_some() {
+            types.float_array() || types.int_array() || has_data_type(types)
+        } else {
+            types.double() || types.float() || types.int()
+        };
+        if usable {
+            self.resolved = Some(Resolved {
+                id,
+                types,
+                writable: unsafe { XPLMCanWriteDataRef(id) == 1 },
+            });
+        }
+        usable
+    }
+
+    /// Reads the transformed value of the target
+    fn read(&self) -> f64 {
+        let Some(resolved) = &self.resolved else {
+            return 0.0;
+        };
+        let raw = match self.target.index {
+            None => {
+                if resolved.types.double() {
+                    handle::<f64>(resolved.id).get()
+                } else if resolved.types.float() {
+                    f64::from(handle::<f32>(resolved.id).get())
+                } else {
+                    f64::from(handle::<i32>(resolved.id).get())
+                }
+            }
+            Some(index) => if resolved.types.float_array() {
+                handle::<[f32]>(resolved.id).get_at(index).map(f64::from)
+            } else if resolved.types.int_array() {
+                handle::<[i32]>(resolved.id).get_at(index).map(f64::from)
+            } else {
+                handle::<[u8]>(resolved.id).get_at(index).map(f64::from)
+            }
+            .unwrap_or_default(),
+        };
+        self.target.apply(raw)
+    }
+
+    /// Writes a value to the target, reversing the transforms
+    fn write(&self, value: f64) {
+        let Some(resolved) = self.resolved.as_ref().filter(|r| r.writable) else {
+            return;
+        };
+        let Some(raw) = self.target.reverse(value) else {
+            return;
+        };
+        match self.target.index {
+            None => {
+                if resolved.types.double() {
+                    handle::<f64>(resolved.id).set(raw);
+                } else if resolved.types.float() {
+                    handle::<f32>(resolved.id).set(f32::from_double(raw));
+                } else {
+                    handle::<i32>(resolved.id).set(i32::from_double(raw));
+                }
+            }
+            Some(index) => {
+                if resolved.types.float_array() {
+                    handle::<[f32]>(resolved.id).set_at(index, f32::from_double(raw));
+                } else if resolved.types.int_array() {
+                    handle::<[i32]>(resolved.id).set_at(index, i32::from_double(raw));
+                } else {
+                    handle::<[u8]>(resolved.id).set_at(index, u8::from_double(raw));
+                }
+            }
+        }
+    }
+}
+
+/// Returns a handle to the dataref `id` as type `T`
+fn handle<T: ?Sized>(id: XPLMDataRef) -> DataRef<T, ReadWrite> {
+    DataRef {
+        id,
+        float_fallback: false,
+        _phantom: PhantomData,
+    }
+}
+
+/// Looks for the targets of all proxies that have not found them yet.
+fn retry_pending() {
+    let pending = PENDING.with_borrow_mut(std::mem::take);
+    let still_pending = pending
+        .into_iter()
+        .filter(|proxy| {
+            proxy
+                .upgrade()
+                .is_some_and(|proxy| !proxy.borrow_mut().resolve())
+        })
+        .collect::<Vec<_>>();
+    PENDING.with_borrow_mut(|pending| pending.extend(still_pending));
+}
+
+/// Handles a message sent to this plugin.
+pub(crate) fn handle_message(message: MessageId, param: *mut c_void) {
+    #[cfg(feature = "XPLM400")]
+    if message == MessageId::DatarefsAdded {
+        retry_pending();
+        return;
+    }
+    // The user's plane has ID 0.
+    if message == MessageId::PlaneLoaded && param.is_null() {
+        retry_pending();
+    }
+}
+
+/// Returns the proxy for a callback refcon
+///
+/// # Safety
+/// `refcon` must be the pointer passed to `XPLMRegisterDataAccessor` by a live [`ProxyData`].
+unsafe fn proxy_inner<'a>(refcon: *mut c_void) -> &'a RefCell<ProxyInner> {
+    // UNWRAP: The refcon will not be null.
+    unsafe { refcon.cast::<RefCell<ProxyInner>>().as_ref().unwrap() }
+}
+
+unsafe extern "C-unwind" fn read_int(refcon: *mut c_void) -> c_int {
+    unsafe { proxy_inner(refcon) }.borrow().read().to_int()
+}
+
+unsafe extern "C-unwind" fn write_int(refcon: *mut c_void, value: c_int) {
+    unsafe { proxy_inner(refcon) }
+        .borrow()
+        .write(f64::from(value));
+}
+
+unsafe extern "C-unwind" fn read_float(refcon: *mut c_void) -> f32 {
+    unsafe { proxy_inner(refcon) }.borrow().read().to_float()
+}
+
+unsafe extern "C-unwind" fn write_float(refcon: *mut c_void, value: f32) {
+    unsafe { proxy_inner(refcon) }
+        .borrow()
+        .write(f64::from(value));
+}
+
+unsafe extern "C-unwind" fn read_double(refcon: *mut c_void) -> f64 {
+    unsafe { proxy_inner(refcon) }.borrow().read()
+}
+
+unsafe extern "C-unwind" fn write_double(refcon: *mut c_void, value: f64) {
+    unsafe { proxy_inner(refcon) }.borrow().write(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{self, NonNull};
+
+    use super::*;
+
+    #[test]
+    fn test_transforms() {
+        let target = ProxyTarget::new("sim/test")
+            .scale(2.0)
+            .offset(1.0)
+            .clamp(0.0, 10.0);
+        assert!((target.apply(3.0) - 7.0).abs() < f64::EPSILON);
+        assert!((target.apply(20.0) - 10.0).abs() < f64::EPSILON);
+        assert!((target.reverse(7.0).unwrap() - 3.0).abs() < f64::EPSILON);
+        // Writes are clamped before the other transforms are reversed
+        assert!((target.reverse(25.0).unwrap() - 4.5).abs() < f64::EPSILON);
+
+        // Reversed and NaN bounds do not panic
+        let reversed = ProxyTarget::new("sim/test").clamp(10.0, 0.0);
+        assert!((reversed.apply(20.0) - 10.0).abs() < f64::EPSILON);
+        let nan = ProxyTarget::new("sim/test").transform(Transform::Clamp {
+            min: f64::NAN,
+            max: 1.0,
+        });
+        assert!((nan.apply(2.0) - 1.0).abs() < f64::EPSILON);
+        // Writes through a scale by zero are ignored
+        assert_eq!(ProxyTarget::new("sim/test").scale(0.0).reverse(1.0), None);
+
+        let inverted = ProxyTarget::new("sim/test").invert();
+        assert!((inverted.apply(0.0) - 1.0).abs() < f64::EPSILON);
+        assert!(inverted.apply(0.5).abs() < f64::EPSILON);
+        assert!((inverted.reverse(0.0).unwrap() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_late_target() {
+        let proxy_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let target_ptr = NonNull::<u8>::dangling()
+            .as_ptr()
+            .wrapping_add(1)
+            .cast::<c_void>();
+        let target_exists = Rc::new(RefCell::new(false));
+        let target_exists_1 = target_exists.clone();
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx.expect().returning_st(move |name| {
+            let name = unsafe { std::ffi::CStr::from_ptr(name) };
+            if name.to_bytes() == b"otherplugin/engines/n1" && *target_exists_1.borrow() {
+                target_ptr
+            } else {
+                ptr::null_mut()
+            }
+        });
+        let callbacks = Rc::new(RefCell::new(None));
+        let callbacks_1 = callbacks.clone();
+        let register_ctx = xplane_sys::XPLMRegisterDataAccessor_context();
+        register_ctx.expect().once().return_once_st(
+            move |_, _, _, _, _, fr, fw, _, _, _, _, _, _, _, _, refcon, _| {
+                *callbacks_1.borrow_mut() = Some((fr.unwrap(), fw.unwrap(), refcon));
+                proxy_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterDataAccessor_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, proxy_ptr));
+        let types_ctx = xplane_sys::XPLMGetDataRefTypes_context();
+        types_ctx
+            .expect()
+            .returning_st(|_| XPLMDataTypeID::FloatArray);
+        let can_write_ctx = xplane_sys::XPLMCanWriteDataRef_context();
+        can_write_ctx.expect().returning_st(|_| 1);
+        let values = Rc::new(RefCell::new([10.0f32, 20.0, 30.0]));
+        let values_1 = values.clone();
+        let get_ctx = xplane_sys::XPLMGetDatavf_context();
+        get_ctx.expect().returning_st(move |id, out, offset, max| {
+            assert_eq!(id, target_ptr);
+            let values = values_1.borrow();
+            let offset = usize::try_from(offset).unwrap();
+            let count = usize::try_from(max).unwrap().min(values.len() - offset);
+            unsafe {
+                ptr::copy_nonoverlapping(values[offset..].as_ptr(), out, count);
+            }
+            i32::try_from(count).unwrap()
+        });
+        let values_2 = values.clone();
+        let set_ctx = xplane_sys::XPLMSetDatavf_context();
+        set_ctx
+            .expect()
+            .returning_st(move |_, input, offset, count| {
+                let offset = usize::try_from(offset).unwrap();
+                let count = usize::try_from(count).unwrap();
+                let input = unsafe { std::slice::from_raw_parts(input, count) };
+                values_2.borrow_mut()[offset..offset + count].copy_from_slice(input);
+            });
+
+        let target = ProxyTarget::element("otherplugin/engines/n1", 1).scale(0.01);
+        let mut proxy = ProxyData::<ReadWrite>::new("xplane_rs/test/n1", target).unwrap();
+        let (fr, fw, refcon) = callbacks.borrow_mut().take().unwrap();
+        assert!(!proxy.is_resolved());
+        assert!(unsafe { fr(refcon) }.abs() < f32::EPSILON);
+
+        *target_exists.borrow_mut() = true;
+        handle_message(MessageId::PlaneLoaded, ptr::null_mut());
+        assert!(proxy.is_resolved());
+        assert!((unsafe { fr(refcon) } - 0.2).abs() < 1e-6);
+        unsafe {
+            fw(refcon, 0.5);
+        }
+        assert!((values.borrow()[1] - 50.0).abs() < 1e-4);
+        proxy.set(0.25);
+        assert!((proxy.get() - 0.25).abs() < 1e-6);
+        drop(proxy);
+    }
+}
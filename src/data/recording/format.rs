@@ -0,0 +1,643 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! The recording file format
+//!
+//! A recording file is a header followed by any number of frames. All numbers are
+//! little-endian.
+//!
+//! The header is:
+//!
+//! | Size | Contents |
+//! |------|----------|
+//! | 4 | The magic bytes `XPDR` |
+//! | 2 | Format version, currently 1 (`u16`) |
+//! | 2 | Number of channels (`u16`) |
+//! | ... | Each channel, as described below |
+//!
+//! Each channel is:
+//!
+//! | Size | Contents |
+//! |------|----------|
+//! | 2 | Length of the dataref name in bytes (`u16`) |
+//! | ... | The dataref name, as UTF-8 |
+//! | 1 | Value type: 0 = int, 1 = float, 2 = double, 3 = int array, 4 = float array, 5 = byte array |
+//! | 4 | Number of elements (`u32`). This is 1 for single values. |
+//!
+//! Each frame is the sim time in seconds (`f64`), followed by the value of each channel in
+//! header order. Ints are `i32`, floats are `f32`, doubles are `f64`, and bytes are `u8`.
+//! Arrays always have the number of elements given in the header.
+//!
+//! Frames are written one at a time while recording, so a recording that was interrupted may end
+//! with an incomplete frame. Readers ignore it.
+
+use std::{
+    fmt::Write as _,
+    io::{self, ErrorKind, Read, Write},
+};
+
+use snafu::prelude::*;
+
+use crate::data::borrowed::{DataValue, DataValueError, DataValueType};
+
+/// The magic bytes at the start of every recording file
+const MAGIC: [u8; 4] = *b"XPDR";
+/// The current format version
+const VERSION: u16 = 1;
+
+/// A dataref recorded in a [`Recording`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    name: String,
+    value_type: DataValueType,
+    len: usize,
+}
+
+impl Channel {
+    /// Creates a channel. `len` is ignored for single values.
+    #[must_use]
+    pub fn new<S: Into<String>>(name: S, value_type: DataValueType, len: usize) -> Self {
+        let len = if is_array(value_type) { len } else { 1 };
+        Channel {
+            name: name.into(),
+            value_type,
+            len,
+        }
+    }
+    /// Returns the name of the dataref
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the type of the recorded values
+    #[must_use]
+    pub fn value_type(&self) -> DataValueType {
+        self.value_type
+    }
+    /// Returns the number of elements in each value. This is 1 for single values.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Returns true if this is an array channel with no elements
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Converts a value to this channel's type, padding or truncating arrays to its length.
+    fn normalize(&self, value: &DataValue) -> Result<DataValue, RecordingError> {
+        let value = value
+            .clone()
+            .convert(self.value_type)
+            .context(ConvertSnafu {
+                channel: &self.name,
+            })?;
+        Ok(match value {
+            DataValue::IntArray(mut v) => {
+                v.resize(self.len, 0);
+                DataValue::IntArray(v)
+            }
+            DataValue::FloatArray(mut v) => {
+                v.resize(self.len, 0.0);
+                DataValue::FloatArray(v)
+            }
+            DataValue::Bytes(mut v) => {
+                v.resize(self.len, 0);
+                DataValue::Bytes(v)
+            }
+            single => single,
+        })
+    }
+}
+
+/// The values of every channel at one time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    time: f64,
+    values: Vec<DataValue>,
+}
+
+impl Frame {
+    /// Returns the sim time when this frame was recorded, in seconds
+    #[must_use]
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    /// Returns the value of each channel, in channel order
+    #[must_use]
+    pub fn values(&self) -> &[DataValue] {
+        &self.values
+    }
+}
+
+/// A recording loaded into memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    channels: Vec<Channel>,
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    /// Reads a recording.
+    /// # Errors
+    /// Returns an error if the data could not be read or is not a valid recording.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, RecordingError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        ensure!(magic == MAGIC, BadMagicSnafu);
+        let version = read_u16(&mut reader)?;
+        ensure!(version == VERSION, UnsupportedVersionSnafu { version });
+        let channel_count = read_u16(&mut reader)?;
+        let mut channels = Vec::with_capacity(usize::from(channel_count));
+        for _ in 0..channel_count {
+            let name_len = read_u16(&mut reader)?;
+            let mut name = vec![0u8; usize::from(name_len)];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| RecordingError::BadName)?;
+            let value_type = type_from_code(read_array::<1>(&mut reader)?[0])?;
+            let len = u32::from_le_bytes(read_array(&mut reader)?);
+            let len = usize::try_from(len).map_err(|_| RecordingError::TooLarge)?;
+            channels.push(Channel::new(name, value_type, len));
+        }
+
+        let mut frames = Vec::new();
+        while let Some(frame) = read_frame(&mut reader, &channels)? {
+            frames.push(frame);
+        }
+        Ok(Recording { channels, frames })
+    }
+
+    /// Writes this recording in the binary format.
+    /// # Errors
+    /// Returns an error if the data could not be written.
+    pub fn write<W: Write>(&self, writer: W) -> Result<W, RecordingError> {
+        let mut writer = RecordingWriter::new(writer, self.channels.clone())?;
+        for frame in &self.frames {
+            writer.write_frame(frame.time, &frame.values)?;
+        }
+        writer.into_inner()
+    }
+
+    /// Writes this recording as CSV.
+    ///
+    /// The first column is the sim time. Each single-value channel has one column with the
+    /// dataref name. Array channels have one column per element, named like `name[0]`, except
+    /// for byte arrays, which have one column containing the bytes as text up to the first NUL.
+    /// # Errors
+    /// Returns an error if the data could not be written.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<W> {
+        let mut line = String::from("time");
+        for channel in &self.channels {
+            match channel.value_type {
+                DataValueType::IntArray | DataValueType::FloatArray => {
+                    for i in 0..channel.len {
+                        line.push(',');
+                        push_csv_field(&mut line, &format!("{}[{i}]", channel.name));
+                    }
+                }
+                _ => {
+                    line.push(',');
+                    push_csv_field(&mut line, &channel.name);
+                }
+            }
+        }
+        writeln!(writer, "{line}")?;
+        for frame in &self.frames {
+            line.clear();
+            // UNWRAP: Writing to a String can not fail.
+            write!(line, "{}", frame.time).unwrap();
+            for value in &frame.values {
+                match value {
+                    DataValue::Int(v) => write!(line, ",{v}").unwrap(),
+                    DataValue::Float(v) => write!(line, ",{v}").unwrap(),
+                    DataValue::Double(v) => write!(line, ",{v}").unwrap(),
+                    DataValue::IntArray(v) => {
+                        for v in v {
+                            write!(line, ",{v}").unwrap();
+                        }
+                    }
+                    DataValue::FloatArray(v) => {
+                        for v in v {
+                            write!(line, ",{v}").unwrap();
+                        }
+                    }
+                    DataValue::Bytes(v) => {
+                        let end = v.iter().position(|&b| b == 0).unwrap_or(v.len());
+                        line.push(',');
+                        push_csv_field(&mut line, &String::from_utf8_lossy(&v[..end]));
+                    }
+                }
+            }
+            writeln!(writer, "{line}")?;
+        }
+        Ok(writer)
+    }
+
+    /// Returns the recorded channels
+    #[must_use]
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Returns the recorded frames, in order
+    #[must_use]
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Returns the time between the first and last frames, in seconds
+    #[must_use]
+    pub fn duration(&self) -> f64 {
+        match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the index of the last frame recorded at or before `offset` seconds after the
+    /// first frame, or [`None`] if there are no frames.
+    #[must_use]
+    pub fn frame_at(&self, offset: f64) -> Option<usize> {
+        let start = self.frames.first()?.time;
+        let after = self
+            .frames
+            .partition_point(|frame| frame.time - start <= offset);
+        Some(after.saturating_sub(1))
+    }
+}
+
+/// Writes a recording one frame at a time
+#[derive(Debug)]
+pub struct RecordingWriter<W: Write> {
+    writer: W,
+    channels: Vec<Channel>,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    /// Creates a writer and writes the header.
+    /// # Errors
+    /// Returns an error if the header could not be written, or if there are too many channels
+    /// or a channel name is too long.
+    pub fn new(mut writer: W, channels: Vec<Channel>) -> Result<Self, RecordingError> {
+        let channel_count = u16::try_from(channels.len()).map_err(|_| RecordingError::TooLarge)?;
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&channel_count.to_le_bytes())?;
+        for channel in &channels {
+            let name_len =
+                u16::try_from(channel.name.len()).map_err(|_| RecordingError::TooLarge)?;
+            let len = u32::try_from(channel.len).map_err(|_| RecordingError::TooLarge)?;
+            writer.write_all(&name_len.to_le_bytes())?;
+            writer.write_all(channel.name.as_bytes())?;
+            writer.write_all(&[type_code(channel.value_type)])?;
+            writer.write_all(&len.to_le_bytes())?;
+        }
+        Ok(RecordingWriter { writer, channels })
+    }
+
+    /// Writes one frame. Values are converted to each channel's type, and arrays are padded
+    /// with zeros or truncated to each channel's length.
+    /// # Errors
+    /// Returns an error if the number of values does not match the number of channels, if a
+    /// value can not be converted, or if the frame could not be written.
+    pub fn write_frame(&mut self, time: f64, values: &[DataValue]) -> Result<(), RecordingError> {
+        ensure!(
+            values.len() == self.channels.len(),
+            ValueCountSnafu {
+                expected: self.channels.len(),
+                actual: values.len(),
+            }
+        );
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&time.to_le_bytes());
+        for (channel, value) in self.channels.iter().zip(values) {
+            match channel.normalize(value)? {
+                DataValue::Int(v) => buffer.extend_from_slice(&v.to_le_bytes()),
+                DataValue::Float(v) => buffer.extend_from_slice(&v.to_le_bytes()),
+                DataValue::Double(v) => buffer.extend_from_slice(&v.to_le_bytes()),
+                DataValue::IntArray(v) => v
+                    .iter()
+                    .for_each(|v| buffer.extend_from_slice(&v.to_le_bytes())),
+                DataValue::FloatArray(v) => v
+                    .iter()
+                    .for_each(|v| buffer.extend_from_slice(&v.to_le_bytes())),
+                DataValue::Bytes(v) => buffer.extend_from_slice(&v),
+            }
+        }
+        // The frame is written all at once, so that an interrupted recording only loses
+        // the last frame.
+        self.writer.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Returns the channels being written
+    #[must_use]
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Flushes the writer and returns it.
+    /// # Errors
+    /// Returns an error if flushing failed.
+    pub fn into_inner(mut self) -> Result<W, RecordingError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Errors that can occur when reading or writing recordings
+#[derive(Snafu, Debug)]
+pub enum RecordingError {
+    /// Reading or writing failed
+    #[snafu(display("I/O error: {source}"))]
+    #[snafu(context(false))]
+    Io {
+        /// The source error.
+        source: io::Error,
+    },
+
+    /// The data does not start with the recording magic bytes
+    #[snafu(display("Not a dataref recording"))]
+    BadMagic,
+
+    /// The recording was written by an unsupported version of the format
+    #[snafu(display("Unsupported recording version {version}"))]
+    UnsupportedVersion {
+        /// The version in the file
+        version: u16,
+    },
+
+    /// A channel has an unknown value type
+    #[snafu(display("Unknown value type {code}"))]
+    BadValueType {
+        /// The type code in the file
+        code: u8,
+    },
+
+    /// A channel name is not valid UTF-8
+    #[snafu(display("Channel name is not valid UTF-8"))]
+    BadName,
+
+    /// There are too many channels, a channel name is too long, or an array is too long
+    #[snafu(display("Too many channels, or a channel is too large"))]
+    TooLarge,
+
+    /// A frame has the wrong number of values
+    #[snafu(display("Frame has {actual} values, but there are {expected} channels"))]
+    ValueCount {
+        /// The number of channels
+        expected: usize,
+        /// The number of values in the frame
+        actual: usize,
+    },
+
+    /// A value could not be converted to its channel's type
+    #[snafu(display("Value for {channel} does not match its channel: {source}"))]
+    Convert {
+        /// The channel name
+        channel: String,
+        /// The conversion error
+        source: DataValueError,
+    },
+}
+
+fn is_array(value_type: DataValueType) -> bool {
+    matches!(
+        value_type,
+        DataValueType::IntArray | DataValueType::FloatArray | DataValueType::Bytes
+    )
+}
+
+fn type_code(value_type: DataValueType) -> u8 {
+    match value_type {
+        DataValueType::Int => 0,
+        DataValueType::Float => 1,
+        DataValueType::Double => 2,
+        DataValueType::IntArray => 3,
+        DataValueType::FloatArray => 4,
+        DataValueType::Bytes => 5,
+    }
+}
+
+fn type_from_code(code: u8) -> Result<DataValueType, RecordingError> {
+    Ok(match code {
+        0 => DataValueType::Int,
+        1 => DataValueType::Float,
+        2 => DataValueType::Double,
+        3 => DataValueType::IntArray,
+        4 => DataValueType::FloatArray,
+        5 => DataValueType::Bytes,
+        _ => return BadValueTypeSnafu { code }.fail(),
+    })
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    read_array(reader).map(u16::from_le_bytes)
+}
+
+/// Reads one frame. Returns [`None`] at the end of the data, including if the last frame is
+/// incomplete.
+fn read_frame(
+    reader: &mut impl Read,
+    channels: &[Channel],
+) -> Result<Option<Frame>, RecordingError> {
+    match read_frame_inner(reader, channels) {
+        Ok(frame) => Ok(Some(frame)),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_frame_inner(reader: &mut impl Read, channels: &[Channel]) -> io::Result<Frame> {
+    let time = f64::from_le_bytes(read_array(reader)?);
+    let mut values = Vec::with_capacity(channels.len());
+    for channel in channels {
+        let value = match channel.value_type {
+            DataValueType::Int => DataValue::Int(i32::from_le_bytes(read_array(reader)?)),
+            DataValueType::Float => DataValue::Float(f32::from_le_bytes(read_array(reader)?)),
+            DataValueType::Double => DataValue::Double(f64::from_le_bytes(read_array(reader)?)),
+            DataValueType::IntArray => DataValue::IntArray(
+                (0..channel.len)
+                    .map(|_| read_array(reader).map(i32::from_le_bytes))
+                    .collect::<Result<_, _>>()?,
+            ),
+            DataValueType::FloatArray => DataValue::FloatArray(
+                (0..channel.len)
+                    .map(|_| read_array(reader).map(f32::from_le_bytes))
+                    .collect::<Result<_, _>>()?,
+            ),
+            DataValueType::Bytes => {
+                // The length comes from the file, so the buffer only grows as bytes are read.
+                let mut bytes = Vec::new();
+                reader
+                    .by_ref()
+                    .take(channel.len as u64)
+                    .read_to_end(&mut bytes)?;
+                if bytes.len() != channel.len {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+                DataValue::Bytes(bytes)
+            }
+        };
+        values.push(value);
+    }
+    Ok(Frame { time, values })
+}
+
+/// Appends a CSV field, quoting it if needed
+fn push_csv_field(line: &mut String, field: &str) {
+    if field.contains([',', '"', '\n', '\r']) {
+        line.push('"');
+        line.push_str(&field.replace('"', "\"\""));
+        line.push('"');
+    } else {
+        line.push_str(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_channels() -> Vec<Channel> {
+        vec![
+            Channel::new("sim/test/int", DataValueType::Int, 0),
+            Channel::new("sim/test/double", DataValueType::Double, 0),
+            Channel::new("sim/test/floats", DataValueType::FloatArray, 2),
+            Channel::new("sim/test/text", DataValueType::Bytes, 4),
+        ]
+    }
+
+    fn write_test_recording() -> Vec<u8> {
+        let mut writer = RecordingWriter::new(Vec::new(), test_channels()).unwrap();
+        writer
+            .write_frame(
+                10.0,
+                &[
+                    DataValue::Int(1),
+                    DataValue::Double(0.5),
+                    DataValue::FloatArray(vec![1.0, 2.0, 3.0]),
+                    DataValue::Bytes(b"ab".to_vec()),
+                ],
+            )
+            .unwrap();
+        writer
+            .write_frame(
+                10.5,
+                &[
+                    // Converted to the channel type
+                    DataValue::Float(2.0),
+                    DataValue::Double(0.75),
+                    DataValue::FloatArray(vec![4.0]),
+                    DataValue::Bytes(b"a,\"b\"".to_vec()),
+                ],
+            )
+            .unwrap();
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = write_test_recording();
+        let recording = Recording::read(data.as_slice()).unwrap();
+        assert_eq!(recording.channels(), test_channels().as_slice());
+        assert_eq!(recording.frames().len(), 2);
+        assert_eq!(
+            recording.frames()[0].values(),
+            [
+                DataValue::Int(1),
+                DataValue::Double(0.5),
+                DataValue::FloatArray(vec![1.0, 2.0]),
+                DataValue::Bytes(b"ab\0\0".to_vec()),
+            ]
+        );
+        assert_eq!(
+            recording.frames()[1].values(),
+            [
+                DataValue::Int(2),
+                DataValue::Double(0.75),
+                DataValue::FloatArray(vec![4.0, 0.0]),
+                DataValue::Bytes(b"a,\"b".to_vec()),
+            ]
+        );
+        assert!((recording.duration() - 0.5).abs() < f64::EPSILON);
+
+        let rewritten = recording.write(Vec::new()).unwrap();
+        assert_eq!(rewritten, data);
+    }
+
+    #[test]
+    fn test_truncated_and_invalid() {
+        let mut data = write_test_recording();
+        // An incomplete last frame is ignored.
+        data.truncate(data.len() - 3);
+        let recording = Recording::read(data.as_slice()).unwrap();
+        assert_eq!(recording.frames().len(), 1);
+
+        // An incomplete header is an error.
+        assert!(matches!(
+            Recording::read(&data[..10]),
+            Err(RecordingError::Io { .. })
+        ));
+        data[0] = b'Y';
+        assert!(matches!(
+            Recording::read(data.as_slice()),
+            Err(RecordingError::BadMagic)
+        ));
+
+        let mut writer = RecordingWriter::new(Vec::new(), test_channels()).unwrap();
+        assert!(matches!(
+            writer.write_frame(0.0, &[DataValue::Int(1)]),
+            Err(RecordingError::ValueCount {
+                expected: 4,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_huge_channel() {
+        // A byte channel claiming to be 4 GiB long, followed by a short frame
+        let mut data = Vec::new();
+        data.extend_from_slice(&MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.push(b'a');
+        data.push(type_code(DataValueType::Bytes));
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0.0f64.to_le_bytes());
+        data.extend_from_slice(b"abc");
+        let recording = Recording::read(data.as_slice()).unwrap();
+        assert!(recording.frames().is_empty());
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let recording = Recording::read(write_test_recording().as_slice()).unwrap();
+        assert_eq!(recording.frame_at(0.0), Some(0));
+        assert_eq!(recording.frame_at(0.49), Some(0));
+        assert_eq!(recording.frame_at(0.5), Some(1));
+        assert_eq!(recording.frame_at(100.0), Some(1));
+    }
+
+    #[test]
+    fn test_csv() {
+        let recording = Recording::read(write_test_recording().as_slice()).unwrap();
+        let csv = String::from_utf8(recording.write_csv(Vec::new()).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "time,sim/test/int,sim/test/double,sim/test/floats[0],sim/test/floats[1],sim/test/text\n\
+             10,1,0.5,1,2,ab\n\
+             10.5,2,0.75,4,0,\"a,\"\"b\"\n"
+        );
+    }
+}
@@ -0,0 +1,524 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{cell::RefCell, fmt, io::Write, marker::PhantomData, rc::Rc, time::Duration};
+
+use snafu::prelude::*;
+
+use crate::flight_loop::{FlightLoop, FlightLoopPhase, LoopResult, LoopState};
+use crate::XPAPI;
+
+use super::{
+    borrowed::{AnyDataRef, DataValue, DataValueType, FindError},
+    ArrayReadWrite, ArrayType, DataReadWrite, ScalarType,
+};
+
+/// The file format, and reading and writing recordings without the sim
+pub mod format;
+
+pub use self::format::{Channel, Frame, Recording, RecordingError, RecordingWriter};
+
+/// The dataref that provides the time for each frame
+const TIME_DATAREF: &str = "sim/time/total_running_time_sec";
+
+/// Records datarefs every flight loop to a [`Write`]
+///
+/// Each frame is written as soon as it is recorded, in the [format](format) described in the
+/// `format` module. Recording stops when the recorder is dropped or finished.
+pub struct Recorder<W: Write + 'static> {
+    state: Rc<RefCell<RecorderState<W>>>,
+    _flight_loop: FlightLoop<()>,
+}
+
+struct RecorderState<W: Write> {
+    /// The writer, or [`None`] if writing failed
+    writer: Option<RecordingWriter<W>>,
+    /// The recorded datarefs, in channel order
+    datarefs: Vec<AnyDataRef>,
+    /// The sim time dataref
+    time: AnyDataRef,
+    paused: bool,
+    /// The error that stopped recording, if any
+    error: Option<RecordingError>,
+}
+
+impl<W: Write + 'static> Recorder<W> {
+    pub(super) fn new<S: AsRef<str>>(
+        output: W,
+        names: &[S],
+        phase: FlightLoopPhase,
+    ) -> Result<Self, RecorderError> {
+        let find = |name: &str| AnyDataRef::find(name).context(FindSnafu { name });
+        let time = find(TIME_DATAREF)?;
+        let datarefs = names
+            .iter()
+            .map(|name| find(name.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let channels = names
+            .iter()
+            .zip(&datarefs)
+            .map(|(name, dataref)| {
+                Channel::new(
+                    name.as_ref(),
+                    dataref.natural_type(),
+                    dataref.array_len().unwrap_or(1),
+                )
+            })
+            .collect();
+        let writer = RecordingWriter::new(output, channels).context(WriteSnafu)?;
+
+        let state = Rc::new(RefCell::new(RecorderState {
+            writer: Some(writer),
+            datarefs,
+            time,
+            paused: false,
+            error: None,
+        }));
+        let loop_state = Rc::clone(&state);
+        let callback = move |_x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+            loop_state.borrow_mut().record_frame();
+            LoopResult::NextLoop
+        };
+        let mut flight_loop = FlightLoop::new(phase, callback, ());
+        flight_loop.schedule_immediate();
+        Ok(Recorder {
+            state,
+            _flight_loop: flight_loop,
+        })
+    }
+
+    /// Stops recording frames until [`Recorder::resume`] is called.
+    pub fn pause(&mut self) {
+        self.state.borrow_mut().paused = true;
+    }
+
+    /// Resumes recording frames.
+    pub fn resume(&mut self) {
+        self.state.borrow_mut().paused = false;
+    }
+
+    /// Returns true if frames are being recorded. This is false if the recorder is paused, or
+    /// if writing failed.
+    #[must_use]
+    pub fn is_recording(&self) -> bool {
+        let state = self.state.borrow();
+        !state.paused && state.writer.is_some()
+    }
+
+    /// Stops recording, flushes the output, and returns it.
+    /// # Errors
+    /// Returns the error that stopped recording early, if any, or an error if flushing failed.
+    /// # Panics
+    /// Panics if the writer is missing without an error being stored. This should not occur.
+    pub fn finish(self) -> Result<W, RecordingError> {
+        let mut state = self.state.borrow_mut();
+        if let Some(e) = state.error.take() {
+            return Err(e);
+        }
+        // UNWRAP: The writer is only removed when an error is stored.
+        state.writer.take().unwrap().into_inner()
+    }
+}
+
+impl<W: Write> RecorderState<W> {
+    fn record_frame(&mut self) {
+        if self.paused {
+            return;
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let time = match self.time.get_as(DataValueType::Double) {
+            Ok(DataValue::Double(time)) => time,
+            _ => f64::NAN,
+        };
+        let values: Vec<DataValue> = self.datarefs.iter().map(AnyDataRef::get).collect();
+        if let Err(e) = writer.write_frame(time, &values) {
+            self.writer = None;
+            self.error = Some(e);
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<W: Write> fmt::Debug for Recorder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("recording", &self.is_recording())
+            .finish()
+    }
+}
+
+/// Errors that can occur when starting a [`Recorder`]
+#[derive(Snafu, Debug)]
+pub enum RecorderError {
+    /// A dataref could not be found
+    #[snafu(display("Could not find dataref {name}: {source}"))]
+    Find {
+        /// The dataref name
+        name: String,
+        /// The reason
+        source: FindError,
+    },
+    /// The header could not be written
+    #[snafu(display("Could not write recording header: {source}"))]
+    Write {
+        /// The source error
+        source: RecordingError,
+    },
+}
+
+/// Plays a [`Recording`] back onto datarefs
+///
+/// Channels of the recording are bound to writable datarefs with [`Player::bind`] and
+/// [`Player::bind_array`]. Channels that are not bound are ignored.
+///
+/// A player starts paused at the beginning of the recording. Every flight loop, if playing,
+/// it advances by the time since the last flight loop multiplied by its speed, and writes the
+/// last frame at or before that position. When it reaches either end of the recording, it pauses.
+pub struct Player {
+    state: Rc<RefCell<PlayerState>>,
+    _flight_loop: FlightLoop<()>,
+}
+
+struct PlayerState {
+    recording: Recording,
+    /// The bound datarefs, and the index of the channel that each one plays
+    targets: Vec<(usize, Box<dyn PlaybackTarget>)>,
+    /// The playback position in seconds after the first frame
+    position: f64,
+    speed: f64,
+    paused: bool,
+    /// The index of the last frame written
+    current_frame: Option<usize>,
+}
+
+impl Player {
+    pub(super) fn new(recording: Recording, phase: FlightLoopPhase) -> Self {
+        let state = Rc::new(RefCell::new(PlayerState {
+            recording,
+            targets: Vec::new(),
+            position: 0.0,
+            speed: 1.0,
+            paused: true,
+            current_frame: None,
+        }));
+        let loop_state = Rc::clone(&state);
+        let callback = move |_x: &mut XPAPI, state: &mut LoopState<()>| -> LoopResult {
+            loop_state
+                .borrow_mut()
+                .advance(state.since_last_call().as_secs_f64());
+            LoopResult::NextLoop
+        };
+        let mut flight_loop = FlightLoop::new(phase, callback, ());
+        flight_loop.schedule_immediate();
+        Player {
+            state,
+            _flight_loop: flight_loop,
+        }
+    }
+
+    /// Plays the channel with the given name onto a single-value dataref.
+    ///
+    /// Values are converted to `T` as if by an `as` cast.
+    /// # Errors
+    /// Returns an error if the recording has no channel with that name.
+    pub fn bind<T, D>(&mut self, channel: &str, dataref: D) -> Result<(), PlaybackError>
+    where
+        T: ScalarType,
+        D: DataReadWrite<T> + 'static,
+    {
+        self.add_target(
+            channel,
+            ScalarTarget {
+                dataref,
+                _phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Plays the channel with the given name onto an array dataref.
+    ///
+    /// Elements are converted as if by an `as` cast.
+    /// # Errors
+    /// Returns an error if the recording has no channel with that name.
+    pub fn bind_array<T, D>(&mut self, channel: &str, dataref: D) -> Result<(), PlaybackError>
+    where
+        T: ArrayType + ?Sized + 'static,
+        T::Element: ScalarType,
+        D: ArrayReadWrite<T> + 'static,
+    {
+        self.add_target(
+            channel,
+            ArrayTarget {
+                dataref,
+                values: Vec::new(),
+                _phantom: PhantomData,
+            },
+        )
+    }
+
+    fn add_target(
+        &mut self,
+        channel: &str,
+        target: impl PlaybackTarget,
+    ) -> Result<(), PlaybackError> {
+        let mut state = self.state.borrow_mut();
+        let index = state
+            .recording
+            .channels()
+            .iter()
+            .position(|c| c.name() == channel)
+            .context(UnknownChannelSnafu { name: channel })?;
+        state.targets.push((index, Box::new(target)));
+        // Write the current frame to the new target on the next flight loop.
+        state.current_frame = None;
+        Ok(())
+    }
+
+    /// Starts or resumes playback.
+    pub fn play(&mut self) {
+        self.state.borrow_mut().paused = false;
+    }
+
+    /// Pauses playback.
+    pub fn pause(&mut self) {
+        self.state.borrow_mut().paused = true;
+    }
+
+    /// Returns true if playback is paused, including when it has reached an end of the recording.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.state.borrow().paused
+    }
+
+    /// Moves playback to `position` after the start of the recording. The frame at that position
+    /// is written on the next flight loop, even if playback is paused.
+    pub fn seek(&mut self, position: Duration) {
+        let mut state = self.state.borrow_mut();
+        state.position = position.as_secs_f64().min(state.recording.duration());
+        state.current_frame = None;
+    }
+
+    /// Returns the current playback position, from the start of the recording
+    #[must_use]
+    pub fn position(&self) -> Duration {
+        Duration::from_secs_f64(self.state.borrow().position)
+    }
+
+    /// Returns the length of the recording
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.state.borrow().recording.duration())
+    }
+
+    /// Sets the playback speed. 1.0 is real time, and negative speeds play backwards.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.state.borrow_mut().speed = speed;
+    }
+
+    /// Returns the playback speed
+    #[must_use]
+    pub fn speed(&self) -> f64 {
+        self.state.borrow().speed
+    }
+}
+
+impl PlayerState {
+    /// Advances playback by `elapsed` seconds of real time, and writes the frame at the new
+    /// position if it has changed.
+    fn advance(&mut self, elapsed: f64) {
+        if !self.paused {
+            let duration = self.recording.duration();
+            self.position += elapsed * self.speed;
+            if self.position >= duration || self.position <= 0.0 {
+                self.position = self.position.clamp(0.0, duration);
+                self.paused = true;
+            }
+        }
+        let frame_index = self.recording.frame_at(self.position);
+        if frame_index == self.current_frame {
+            return;
+        }
+        self.current_frame = frame_index;
+        let Some(frame_index) = frame_index else {
+            return;
+        };
+        let values = self.recording.frames()[frame_index].values();
+        for (channel, target) in &mut self.targets {
+            target.apply(&values[*channel]);
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for Player {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Player")
+            .field("position", &state.position)
+            .field("speed", &state.speed)
+            .field("paused", &state.paused)
+            .field("targets", &state.targets.len())
+            .finish()
+    }
+}
+
+/// Errors that can occur when setting up playback
+#[derive(Snafu, Debug)]
+pub enum PlaybackError {
+    /// The recording does not contain the requested channel
+    #[snafu(display("No channel named {name} in recording"))]
+    UnknownChannel {
+        /// The channel name
+        name: String,
+    },
+}
+
+/// A dataref that recorded values can be written to
+trait PlaybackTarget: 'static {
+    fn apply(&mut self, value: &DataValue);
+}
+
+struct ScalarTarget<T, D> {
+    dataref: D,
+    _phantom: PhantomData<fn(T)>,
+}
+
+impl<T, D> PlaybackTarget for ScalarTarget<T, D>
+where
+    T: ScalarType,
+    D: DataReadWrite<T> + 'static,
+{
+    fn apply(&mut self, value: &DataValue) {
+        let value = match value {
+            DataValue::Int(v) => T::from_int(*v),
+            DataValue::Float(v) => T::from_float(*v),
+            DataValue::Double(v) => T::from_double(*v),
+            DataValue::IntArray(v) => match v.first() {
+                Some(v) => T::from_int(*v),
+                None => return,
+            },
+            DataValue::FloatArray(v) => match v.first() {
+                Some(v) => T::from_float(*v),
+                None => return,
+            },
+            DataValue::Bytes(v) => match v.first() {
+                Some(v) => T::from_int(i32::from(*v)),
+                None => return,
+            },
+        };
+        self.dataref.set(value);
+    }
+}
+
+struct ArrayTarget<T: ArrayType + ?Sized, D> {
+    dataref: D,
+    /// Reused buffer for converted values
+    values: Vec<T::Element>,
+    _phantom: PhantomData<fn(&T)>,
+}
+
+impl<T, D> PlaybackTarget for ArrayTarget<T, D>
+where
+    T: ArrayType + ?Sized + 'static,
+    T::Element: ScalarType,
+    D: ArrayReadWrite<T> + 'static,
+{
+    fn apply(&mut self, value: &DataValue) {
+        self.values.clear();
+        match value {
+            DataValue::Int(v) => self.values.push(T::Element::from_int(*v)),
+            DataValue::Float(v) => self.values.push(T::Element::from_float(*v)),
+            DataValue::Double(v) => self.values.push(T::Element::from_double(*v)),
+            DataValue::IntArray(v) => self
+                .values
+                .extend(v.iter().map(|v| T::Element::from_int(*v))),
+            DataValue::FloatArray(v) => self
+                .values
+                .extend(v.iter().map(|v| T::Element::from_float(*v))),
+            DataValue::Bytes(v) => self
+                .values
+                .extend(v.iter().map(|v| T::Element::from_int(i32::from(*v)))),
+        }
+        self.dataref.set(&self.values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::data::DataRead;
+
+    struct FakeData(Rc<Cell<f32>>);
+
+    impl DataRead<f32> for FakeData {
+        fn get(&self) -> f32 {
+            self.0.get()
+        }
+    }
+
+    impl DataReadWrite<f32> for FakeData {
+        fn set(&mut self, value: f32) {
+            self.0.set(value);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_playback() {
+        let mut writer = RecordingWriter::new(
+            Vec::new(),
+            vec![Channel::new("sim/test/value", DataValueType::Float, 1)],
+        )
+        .unwrap();
+        for (time, value) in [(100.0, 1.0), (101.0, 2.0), (102.0, 3.0)] {
+            writer
+                .write_frame(time, &[DataValue::Float(value)])
+                .unwrap();
+        }
+        let recording = Recording::read(writer.into_inner().unwrap().as_slice()).unwrap();
+
+        let value = Rc::new(Cell::new(0.0));
+        let mut state = PlayerState {
+            recording,
+            targets: Vec::new(),
+            position: 0.0,
+            speed: 1.0,
+            paused: true,
+            current_frame: None,
+        };
+        state.targets.push((
+            0,
+            Box::new(ScalarTarget {
+                dataref: FakeData(value.clone()),
+                _phantom: PhantomData,
+            }),
+        ));
+
+        // Paused: the first frame is written, but playback does not move.
+        state.advance(0.5);
+        assert_eq!(value.get(), 1.0);
+        assert_eq!(state.position, 0.0);
+
+        state.paused = false;
+        state.speed = 2.0;
+        state.advance(0.5);
+        assert_eq!(value.get(), 2.0);
+
+        // Playback stops at the end.
+        state.advance(5.0);
+        assert_eq!(value.get(), 3.0);
+        assert_eq!(state.position, 2.0);
+        assert!(state.paused);
+
+        // Seeking while paused writes the new frame.
+        state.position = 0.2;
+        state.current_frame = None;
+        state.advance(0.0);
+        assert_eq!(value.get(), 1.0);
+    }
+}
@@ -3,15 +3,22 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::{
+    cell::Cell,
     ffi::{c_void, CString, NulError},
     marker::PhantomData,
 };
 
 use snafu::prelude::*;
-use xplane_sys::{XPLMFindDataRef, XPLMShareData, XPLMUnshareData};
+use xplane_sys::{XPLMDataRef, XPLMFindDataRef, XPLMShareData, XPLMUnshareData};
 
 use crate::{
-    data::{borrowed::DataRef, DataType, ReadWrite},
+    data::{
+        borrowed::DataRef,
+        editor,
+        owned::{OverflowError, OverflowPolicy},
+        ArrayRead, ArrayReadWrite, ArrayType, DataRead, DataReadWrite, DataType, ReadWrite,
+        StrRead, StrReadWrite,
+    },
     make_x, XPAPI,
 };
 
@@ -33,7 +40,9 @@ pub enum SharedDataError {
 }
 
 /// A shared dataref. Your [`SharedDataHandler::data_changed`] will be called whenever the value of the dataref changes.
-/// You will need to use the function to read the value. Weird, I know.
+///
+/// The value can also be read and written at any time through [`DataRead`], [`DataReadWrite`] and
+/// the array and string traits. The underlying dataref is found the first time it is needed.
 pub struct SharedData<T: DataType + ?Sized + 'static> {
     ctx: *mut SharedDataContext<T>,
     _phantom: PhantomData<(*mut (), T)>,
@@ -44,11 +53,32 @@ impl<T: DataType + ?Sized + 'static> SharedData<T> {
         name: S,
         handler: impl SharedDataHandler<T>,
     ) -> Result<SharedData<T>, SharedDataError> {
-        let name = CString::new(name)?;
         let handler: *mut dyn SharedDataHandler<T> = Box::into_raw(Box::new(handler));
+        Self::share(name, Some(handler))
+    }
+
+    pub(super) fn new_without_handler<S: Into<Vec<u8>>>(
+        name: S,
+    ) -> Result<SharedData<T>, SharedDataError> {
+        Self::share(name, None)
+    }
+
+    fn share<S: Into<Vec<u8>>>(
+        name: S,
+        handler: Option<*mut dyn SharedDataHandler<T>>,
+    ) -> Result<SharedData<T>, SharedDataError> {
+        let name = match CString::new(name) {
+            Ok(name) => name,
+            Err(e) => {
+                if let Some(handler) = handler {
+                    let _ = unsafe { Box::from_raw(handler) };
+                }
+                return Err(e.into());
+            }
+        };
         let ctx = Box::into_raw(Box::new(SharedDataContext {
             name,
-            dref: None,
+            dref: Cell::new(None),
             handler,
             _phantom: PhantomData,
         }));
@@ -61,14 +91,83 @@ impl<T: DataType + ?Sized + 'static> SharedData<T> {
             )
         };
         if res == 1 {
+            editor::announce(unsafe { &(*ctx).name });
             Ok(SharedData {
                 ctx,
                 _phantom: PhantomData,
             })
         } else {
+            let _ = unsafe { Box::from_raw(ctx) };
             WrongTypeSnafu.fail()
         }
     }
+
+    /// Returns a handle to the underlying dataref, finding it if this has not been done yet
+    fn dataref(&self) -> DataRef<T, ReadWrite> {
+        // The context is only accessed through shared references, because handlers can read
+        // the value while the context is in use.
+        // UNWRAP: This will not be a null pointer.
+        let ctx = unsafe { self.ctx.as_ref().unwrap() };
+        ctx.dataref()
+    }
+}
+
+impl<T: DataType + 'static> DataRead<T> for SharedData<T>
+where
+    DataRef<T, ReadWrite>: DataRead<T>,
+{
+    fn get(&self) -> T {
+        self.dataref().get()
+    }
+}
+
+impl<T: DataType + 'static> DataReadWrite<T> for SharedData<T>
+where
+    DataRef<T, ReadWrite>: DataReadWrite<T>,
+{
+    fn set(&mut self, value: T) {
+        self.dataref().set(value);
+    }
+}
+
+impl<T: ArrayType + ?Sized + 'static> ArrayRead<T> for SharedData<T>
+where
+    DataRef<T, ReadWrite>: ArrayRead<T>,
+{
+    fn get_range(&self, offset: usize, dest: &mut [T::Element]) -> usize {
+        self.dataref().get_range(offset, dest)
+    }
+    fn len(&self) -> usize {
+        self.dataref().len()
+    }
+}
+
+impl<T: ArrayType + ?Sized + 'static> ArrayReadWrite<T> for SharedData<T>
+where
+    DataRef<T, ReadWrite>: ArrayReadWrite<T>,
+{
+    fn set_range(&mut self, offset: usize, values: &[T::Element]) {
+        self.dataref().set_range(offset, values);
+    }
+}
+
+impl StrRead for SharedData<str> {
+    fn get_bytes(&self) -> Vec<u8> {
+        self.dataref().get_bytes()
+    }
+    fn max_len(&self) -> usize {
+        self.dataref().max_len()
+    }
+}
+
+impl StrReadWrite for SharedData<str> {
+    fn set_with_policy(
+        &mut self,
+        value: &str,
+        policy: OverflowPolicy,
+    ) -> Result<(), OverflowError> {
+        self.dataref().set_with_policy(value, policy)
+    }
 }
 
 impl<T: DataType + ?Sized + 'static> Drop for SharedData<T> {
@@ -88,14 +187,34 @@ impl<T: DataType + ?Sized + 'static> Drop for SharedData<T> {
 
 struct SharedDataContext<T: DataType + ?Sized + 'static> {
     name: CString,
-    dref: Option<DataRef<T, ReadWrite>>,
-    handler: *mut dyn SharedDataHandler<T>,
+    /// The ID of the shared dataref, once it has been found
+    dref: Cell<Option<XPLMDataRef>>,
+    handler: Option<*mut dyn SharedDataHandler<T>>,
     _phantom: PhantomData<(*mut (), T)>,
 }
 
+impl<T: DataType + ?Sized + 'static> SharedDataContext<T> {
+    /// Returns the shared dataref, finding it if this has not been done yet
+    fn dataref(&self) -> DataRef<T, ReadWrite> {
+        let id = self.dref.get().unwrap_or_else(|| {
+            // X-Plane promises this dataref exists.
+            let id = unsafe { XPLMFindDataRef(self.name.as_ptr()) };
+            self.dref.set(Some(id));
+            id
+        });
+        DataRef {
+            id,
+            float_fallback: false,
+            _phantom: PhantomData,
+        }
+    }
+}
+
 impl<T: DataType + ?Sized> Drop for SharedDataContext<T> {
     fn drop(&mut self) {
-        let _ = unsafe { Box::from_raw(self.handler) };
+        if let Some(handler) = self.handler {
+            let _ = unsafe { Box::from_raw(handler) };
+        }
     }
 }
 
@@ -108,25 +227,88 @@ where
     fn data_changed(&mut self, x: &mut XPAPI, dref: &mut DataRef<T, ReadWrite>);
 }
 
+impl<T, F> SharedDataHandler<T> for F
+where
+    T: DataType + ?Sized + 'static,
+    F: FnMut(&mut XPAPI, &mut DataRef<T, ReadWrite>) + 'static,
+{
+    fn data_changed(&mut self, x: &mut XPAPI, dref: &mut DataRef<T, ReadWrite>) {
+        self(x, dref);
+    }
+}
+
 unsafe extern "C-unwind" fn handle_shared_data_change<T: DataType + ?Sized + 'static>(
     refcon: *mut c_void,
 ) {
     let ctx = unsafe {
-        refcon.cast::<SharedDataContext<T>>().as_mut().unwrap() // UNWRAP: This should never be null.
+        refcon.cast::<SharedDataContext<T>>().as_ref().unwrap() // UNWRAP: This should never be null.
     };
-    let dref = if let Some(ref mut dref) = ctx.dref {
-        dref
-    } else {
-        let dref = DataRef {
-            id: unsafe {
-                XPLMFindDataRef(ctx.name.as_ptr()) // X-Plane promises this dataref exists.
-            },
-            _phantom: PhantomData,
-        };
-        ctx.dref = Some(dref);
-        ctx.dref.as_mut().unwrap() // UNWRAP: We just set this to Some.
+    let Some(handler) = ctx.handler else {
+        return;
     };
-    let cb = unsafe { ctx.handler.as_mut().unwrap() }; // UNWRAP: This will not be a null pointer.
+    let mut dref = ctx.dataref();
+    let cb = unsafe { handler.as_mut().unwrap() }; // UNWRAP: This will not be a null pointer.
     let mut x = make_x();
-    cb.data_changed(&mut x, dref);
+    cb.data_changed(&mut x, &mut dref);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, ptr::NonNull, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_shared_access() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let callback = Rc::new(RefCell::new(None));
+        let callback_1 = callback.clone();
+        let share_ctx = xplane_sys::XPLMShareData_context();
+        share_ctx
+            .expect()
+            .once()
+            .return_once_st(move |_, _, cb, refcon| {
+                *callback_1.borrow_mut() = Some((cb.unwrap(), refcon));
+                1
+            });
+        let find_ctx = xplane_sys::XPLMFindDataRef_context();
+        find_ctx
+            .expect()
+            .once()
+            .return_once_st(move |_| expected_ptr);
+        let value = Rc::new(RefCell::new(0));
+        let value_1 = value.clone();
+        let set_ctx = xplane_sys::XPLMSetDatai_context();
+        set_ctx.expect().returning_st(move |id, v| {
+            assert_eq!(id, expected_ptr);
+            *value_1.borrow_mut() = v;
+        });
+        let value_2 = value.clone();
+        let get_ctx = xplane_sys::XPLMGetDatai_context();
+        get_ctx.expect().returning_st(move |id| {
+            assert_eq!(id, expected_ptr);
+            *value_2.borrow()
+        });
+        let unshare_ctx = xplane_sys::XPLMUnshareData_context();
+        unshare_ctx.expect().once().return_once_st(|_, _, _, _| 1);
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let seen_1 = seen.clone();
+        let mut shared = SharedData::<i32>::new(
+            "xplane_rs/test/shared",
+            move |_: &mut XPAPI, dref: &mut DataRef<i32, ReadWrite>| {
+                seen_1.borrow_mut().push(dref.get());
+            },
+        )
+        .unwrap();
+        shared.set(3);
+        assert_eq!(shared.get(), 3);
+        let (cb, refcon) = callback.borrow_mut().take().unwrap();
+        *value.borrow_mut() = 8;
+        unsafe {
+            cb(refcon);
+        }
+        assert_eq!(*seen.borrow(), [8]);
+        drop(shared);
+    }
 }
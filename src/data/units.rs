@@ -0,0 +1,194 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    f64::consts::PI,
+    fmt::{self, Display},
+};
+
+use xplane_sys::XPLMDataTypeID;
+
+use super::{
+    borrowed::{dataref_type, DataRef},
+    impl_type, DataRead, DataReadWrite, DataType, ReadWrite, ScalarType,
+};
+
+/// Meters in one foot
+const METERS_PER_FOOT: f64 = 0.3048;
+/// Meters per second in one knot
+const METERS_PER_SECOND_PER_KNOT: f64 = 1852.0 / 3600.0;
+/// Radians in one degree
+const RADIANS_PER_DEGREE: f64 = PI / 180.0;
+
+/// Implements `DataType`, `ScalarType` and `DataRef` access for a unit stored as `$inner_type`
+macro_rules! unit_storage {
+    ($name:ident($inner_type:ty) as $sim_type:ident;) => {
+        impl_type!($name<$inner_type> as XPLMDataTypeID::$sim_type);
+
+        dataref_type! {
+            native $name($inner_type);
+        }
+
+        impl ScalarType for $name<$inner_type> {
+            fn to_int(self) -> i32 {
+                self.0.to_int()
+            }
+            fn to_float(self) -> f32 {
+                self.0.to_float()
+            }
+            fn to_double(self) -> f64 {
+                self.0.to_double()
+            }
+            fn from_int(value: i32) -> Self {
+                $name(<$inner_type>::from_int(value))
+            }
+            fn from_float(value: f32) -> Self {
+                $name(<$inner_type>::from_float(value))
+            }
+            fn from_double(value: f64) -> Self {
+                $name(<$inner_type>::from_double(value))
+            }
+        }
+    };
+}
+
+/// Defines a unit newtype
+macro_rules! unit_type {
+    ($(#[$meta:meta])* $name:ident, $symbol:literal) => {
+        $(#[$meta])*
+        ///
+        /// `T` is the type that X-Plane stores the value as: [`f32`] (the default) for float
+        /// datarefs, or [`f64`] for double datarefs.
+        #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+        #[repr(transparent)]
+        pub struct $name<T = f32>(pub T);
+
+        unit_storage! {
+            $name(f32) as Float;
+        }
+
+        unit_storage! {
+            $name(f64) as Double;
+        }
+
+        impl From<$name<f32>> for $name<f64> {
+            fn from(value: $name<f32>) -> Self {
+                $name(f64::from(value.0))
+            }
+        }
+
+        impl<T: Display> Display for $name<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)?;
+                f.write_str(concat!(" ", $symbol))
+            }
+        }
+    };
+}
+
+/// Implements conversions between two units of the same quantity.
+/// One `$large` is `$factor` `$small`s.
+macro_rules! unit_conversion {
+    ($small:ident, $large:ident, $factor:expr) => {
+        impl From<$large<f64>> for $small<f64> {
+            fn from(value: $large<f64>) -> Self {
+                $small(value.0 * $factor)
+            }
+        }
+
+        impl From<$small<f64>> for $large<f64> {
+            fn from(value: $small<f64>) -> Self {
+                $large(value.0 / $factor)
+            }
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        impl From<$large<f32>> for $small<f32> {
+            fn from(value: $large<f32>) -> Self {
+                $small((f64::from(value.0) * $factor) as f32)
+            }
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        impl From<$small<f32>> for $large<f32> {
+            fn from(value: $small<f32>) -> Self {
+                $large((f64::from(value.0) / $factor) as f32)
+            }
+        }
+    };
+}
+
+unit_type! {
+    /// A distance in meters
+    Meters, "m"
+}
+
+unit_type! {
+    /// A distance in feet
+    Feet, "ft"
+}
+
+unit_type! {
+    /// A speed in knots
+    Knots, "kt"
+}
+
+unit_type! {
+    /// A speed in meters per second
+    MetersPerSecond, "m/s"
+}
+
+unit_type! {
+    /// An angle in degrees
+    Degrees, "°"
+}
+
+unit_type! {
+    /// An angle in radians
+    Radians, "rad"
+}
+
+unit_conversion!(Meters, Feet, METERS_PER_FOOT);
+unit_conversion!(MetersPerSecond, Knots, METERS_PER_SECOND_PER_KNOT);
+unit_conversion!(Radians, Degrees, RADIANS_PER_DEGREE);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let feet: Feet<f64> = Meters(1000.0f64).into();
+        assert!((feet.0 - 3280.84).abs() < 0.01);
+        let meters: Meters<f64> = feet.into();
+        assert!((meters.0 - 1000.0).abs() < 1e-9);
+
+        let mps: MetersPerSecond = Knots(100.0).into();
+        assert!((mps.0 - 51.444_443).abs() < 1e-4);
+
+        let radians: Radians = Degrees(180.0).into();
+        assert!((radians.0 - std::f32::consts::PI).abs() < 1e-6);
+
+        let wide: Meters<f64> = Meters(2.5f32).into();
+        assert_eq!(wide, Meters(2.5));
+
+        assert_eq!(format!("{:.1}", Feet(12.34f32)), "12.3 ft");
+    }
+
+    #[test]
+    fn test_dataref_reads_raw_value() {
+        let get_ctx = xplane_sys::XPLMGetDataf_context();
+        get_ctx.expect().once().return_const(304.8f32);
+
+        let dataref = DataRef::<Meters> {
+            id: std::ptr::null_mut(),
+            float_fallback: false,
+            _phantom: std::marker::PhantomData,
+        };
+        let altitude = dataref.get();
+        assert!((altitude.0 - 304.8).abs() < f32::EPSILON);
+        let altitude: Feet = altitude.into();
+        assert!((altitude.0 - 1000.0).abs() < 1e-3);
+    }
+}
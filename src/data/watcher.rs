@@ -0,0 +1,597 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{cell::RefCell, fmt, marker::PhantomData, mem, rc::Rc, time::Duration};
+
+use crate::{
+    flight_loop::{FlightLoop, FlightLoopPhase, LoopResult, LoopState},
+    XPAPI,
+};
+
+use super::{ArrayRead, ArrayType, DataRead, ScalarType};
+
+/// Watches datarefs for changes, and calls handlers when their values change
+///
+/// Datarefs are checked from an internal flight loop, at the phase and rate chosen when the
+/// watcher was created. Each handler is called with the previous and current values of its
+/// dataref. The previous value is the last value reported to the handler, so a slow drift that
+/// stays inside a deadband on every check is still reported once it crosses the deadband.
+///
+/// Dropping the watcher stops all checks.
+pub struct DataWatcher {
+    /// The watches and schedule, shared with the flight loop callback
+    inner: Rc<RefCell<WatcherInner>>,
+    /// The flight loop that checks the watches
+    flight_loop: FlightLoop<()>,
+    /// The ID to give to the next watch
+    next_id: u64,
+}
+
+/// Identifies a watch registered with a [`DataWatcher`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WatchId(u64);
+
+/// One changed element of an array dataref
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementChange<E> {
+    /// The index of the element
+    pub index: usize,
+    /// The previous value, or [`None`] if the array was shorter before
+    pub old: Option<E>,
+    /// The current value, or [`None`] if the array is shorter now
+    pub new: Option<E>,
+}
+
+struct WatcherInner {
+    watches: Vec<(WatchId, Box<dyn Watch>)>,
+    rate: LoopResult,
+    /// The IDs of the watches taken out of `watches` while their handlers are called
+    checking: Vec<WatchId>,
+    /// The IDs of watches in `checking` that were removed by a handler
+    unwatched: Vec<WatchId>,
+}
+
+impl WatcherInner {
+    /// Checks every watch, calling handlers without holding a borrow of `inner`, so that
+    /// handlers can add and remove watches.
+    fn check_all(inner: &RefCell<WatcherInner>, x: &mut XPAPI) -> LoopResult {
+        let mut watches = {
+            let mut inner = inner.borrow_mut();
+            inner.checking = inner.watches.iter().map(|(id, _)| *id).collect();
+            mem::take(&mut inner.watches)
+        };
+        for (_, watch) in &mut watches {
+            watch.check(x);
+        }
+        let mut inner = inner.borrow_mut();
+        let unwatched = mem::take(&mut inner.unwatched);
+        inner.checking.clear();
+        watches.retain(|(id, _)| !unwatched.contains(id));
+        // Watches added by handlers go after the existing ones
+        watches.append(&mut inner.watches);
+        inner.watches = watches;
+        inner.rate
+    }
+}
+
+/// Replaces a rate that X-Plane would not read as an interval with [`LoopResult::NextLoop`]
+///
+/// X-Plane reads zero as deactivating the flight loop and negative numbers as a count of loops.
+fn checked_rate(rate: LoopResult) -> LoopResult {
+    match rate {
+        LoopResult::Seconds(seconds) => match Duration::try_from_secs_f32(seconds) {
+            Ok(interval) if !interval.is_zero() => rate,
+            _ => LoopResult::NextLoop,
+        },
+        LoopResult::Loops(0) => LoopResult::NextLoop,
+        LoopResult::Loops(_) | LoopResult::NextLoop | LoopResult::Deactivate => rate,
+    }
+}
+
+impl DataWatcher {
+    pub(super) fn new(phase: FlightLoopPhase, rate: LoopResult) -> Self {
+        let inner = Rc::new(RefCell::new(WatcherInner {
+            watches: Vec::new(),
+            rate: LoopResult::Deactivate,
+            checking: Vec::new(),
+            unwatched: Vec::new(),
+        }));
+        let loop_inner = Rc::clone(&inner);
+        let callback = move |x: &mut XPAPI, _state: &mut LoopState<()>| -> LoopResult {
+            WatcherInner::check_all(&loop_inner, x)
+        };
+        let mut watcher = DataWatcher {
+            inner,
+            flight_loop: FlightLoop::new(phase, callback, ()),
+            next_id: 0,
+        };
+        watcher.set_rate(rate);
+        watcher
+    }
+
+    /// Changes how often datarefs are checked.
+    ///
+    /// [`LoopResult::Deactivate`] pauses checking until the rate is changed again. A number of
+    /// seconds that is not positive and finite, or zero loops, checks on every loop like
+    /// [`LoopResult::NextLoop`].
+    pub fn set_rate(&mut self, rate: LoopResult) {
+        let rate = checked_rate(rate);
+        self.inner.borrow_mut().rate = rate;
+        match rate {
+            LoopResult::Seconds(seconds) => self
+                .flight_loop
+                .schedule_after(Duration::from_secs_f32(seconds)),
+            LoopResult::Loops(loops) => self.flight_loop.schedule_after_loops(loops),
+            LoopResult::NextLoop => self.flight_loop.schedule_immediate(),
+            LoopResult::Deactivate => self.flight_loop.deactivate(),
+        }
+    }
+
+    /// Calls `handler` with the old and new values every time the value of `dataref` changes.
+    pub fn watch<T, D>(
+        &mut self,
+        dataref: D,
+        handler: impl FnMut(&mut XPAPI, T, T) + 'static,
+    ) -> WatchId
+    where
+        T: ScalarType + PartialEq,
+        D: DataRead<T> + 'static,
+    {
+        self.add(ScalarWatch::new(dataref, None, handler))
+    }
+
+    /// Calls `handler` with the old and new values every time the value of `dataref` changes
+    /// by more than `deadband`.
+    pub fn watch_with_deadband<T, D>(
+        &mut self,
+        dataref: D,
+        deadband: f64,
+        handler: impl FnMut(&mut XPAPI, T, T) + 'static,
+    ) -> WatchId
+    where
+        T: ScalarType + PartialEq,
+        D: DataRead<T> + 'static,
+    {
+        self.add(ScalarWatch::new(dataref, Some(deadband), handler))
+    }
+
+    /// Calls `handler` with the elements that changed every time any element of `dataref`
+    /// changes, or its length changes.
+    pub fn watch_array<T, D>(
+        &mut self,
+        dataref: D,
+        handler: impl FnMut(&mut XPAPI, &[ElementChange<T::Element>]) + 'static,
+    ) -> WatchId
+    where
+        T: ArrayType + ?Sized + 'static,
+        T::Element: ScalarType + PartialEq,
+        D: ArrayRead<T> + 'static,
+    {
+        self.add(ArrayWatch::new(dataref, None, handler))
+    }
+
+    /// Calls `handler` with the elements that changed every time an element of `dataref`
+    /// changes by more than `epsilon`, or its length changes.
+    pub fn watch_array_with_epsilon<T, D>(
+        &mut self,
+        dataref: D,
+        epsilon: f64,
+        handler: impl FnMut(&mut XPAPI, &[ElementChange<T::Element>]) + 'static,
+    ) -> WatchId
+    where
+        T: ArrayType + ?Sized + 'static,
+        T::Element: ScalarType + PartialEq,
+        D: ArrayRead<T> + 'static,
+    {
+        self.add(ArrayWatch::new(dataref, Some(epsilon), handler))
+    }
+
+    /// Calls `handler` with the old and new strings every time the value of `dataref` changes.
+    ///
+    /// Values that are not valid UTF-8 are converted lossily.
+    pub fn watch_string<D>(
+        &mut self,
+        dataref: D,
+        handler: impl FnMut(&mut XPAPI, &str, &str) + 'static,
+    ) -> WatchId
+    where
+        D: ArrayRead<[u8]> + 'static,
+    {
+        self.add(StringWatch::new(dataref, handler))
+    }
+
+    /// Stops watching a dataref. Returns false if the watch had already been removed.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        let len_before = inner.watches.len();
+        inner.watches.retain(|(watch_id, _)| *watch_id != id);
+        if inner.watches.len() != len_before {
+            return true;
+        }
+        // The watch may be out of the list while handlers are called
+        if inner.checking.contains(&id) && !inner.unwatched.contains(&id) {
+            inner.unwatched.push(id);
+            return true;
+        }
+        false
+    }
+
+    /// Returns the number of datarefs being watched
+    #[must_use]
+    pub fn len(&self) -> usize {
+        let inner = self.inner.borrow();
+        inner.watches.len() + inner.checking.len() - inner.unwatched.len()
+    }
+
+    /// Returns true if no datarefs are being watched
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn add(&mut self, watch: impl Watch) -> WatchId {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.inner.borrow_mut().watches.push((id, Box::new(watch)));
+        id
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for DataWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataWatcher")
+            .field("watches", &self.len())
+            .field("rate", &self.inner.borrow().rate)
+            .finish()
+    }
+}
+
+/// A dataref being watched
+trait Watch: 'static {
+    /// Reads the dataref, and calls the handler if it has changed.
+    fn check(&mut self, x: &mut XPAPI);
+}
+
+/// Returns true if `old` and `new` differ by more than `band`, or differ at all if there is no
+/// band.
+fn changed<T: ScalarType + PartialEq>(old: T, new: T, band: Option<f64>) -> bool {
+    match band {
+        Some(band) => (new.to_double() - old.to_double()).abs() > band,
+        None => old != new,
+    }
+}
+
+struct ScalarWatch<T, D, H> {
+    dataref: D,
+    band: Option<f64>,
+    last: T,
+    handler: H,
+}
+
+impl<T, D, H> ScalarWatch<T, D, H>
+where
+    D: DataRead<T>,
+{
+    fn new(dataref: D, band: Option<f64>, handler: H) -> Self {
+        let last = dataref.get();
+        ScalarWatch {
+            dataref,
+            band,
+            last,
+            handler,
+        }
+    }
+}
+
+impl<T, D, H> Watch for ScalarWatch<T, D, H>
+where
+    T: ScalarType + PartialEq,
+    D: DataRead<T> + 'static,
+    H: FnMut(&mut XPAPI, T, T) + 'static,
+{
+    fn check(&mut self, x: &mut XPAPI) {
+        let new = self.dataref.get();
+        if changed(self.last, new, self.band) {
+            let old = self.last;
+            self.last = new;
+            (self.handler)(x, old, new);
+        }
+    }
+}
+
+struct ArrayWatch<T: ArrayType + ?Sized, D, H> {
+    dataref: D,
+    band: Option<f64>,
+    last: Vec<T::Element>,
+    current: Vec<T::Element>,
+    changes: Vec<ElementChange<T::Element>>,
+    handler: H,
+    _phantom: PhantomData<fn(&T)>,
+}
+
+impl<T, D, H> ArrayWatch<T, D, H>
+where
+    T: ArrayType + ?Sized,
+    T::Element: ScalarType,
+    D: ArrayRead<T>,
+{
+    fn new(dataref: D, band: Option<f64>, handler: H) -> Self {
+        let mut last = Vec::new();
+        read_array(&dataref, &mut last);
+        ArrayWatch {
+            dataref,
+            band,
+            last,
+            current: Vec::new(),
+            changes: Vec::new(),
+            handler,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Reads all of `dataref` into `dest`, reusing its allocation.
+fn read_array<T, D>(dataref: &D, dest: &mut Vec<T::Element>)
+where
+    T: ArrayType + ?Sized,
+    T::Element: ScalarType,
+    D: ArrayRead<T>,
+{
+    dest.clear();
+    dest.resize(dataref.len(), T::Element::from_int(0));
+    let read = dataref.get(dest);
+    dest.truncate(read);
+}
+
+impl<T, D, H> Watch for ArrayWatch<T, D, H>
+where
+    T: ArrayType + ?Sized + 'static,
+    T::Element: ScalarType + PartialEq,
+    D: ArrayRead<T> + 'static,
+    H: FnMut(&mut XPAPI, &[ElementChange<T::Element>]) + 'static,
+{
+    fn check(&mut self, x: &mut XPAPI) {
+        read_array(&self.dataref, &mut self.current);
+        self.changes.clear();
+        for index in 0..self.last.len().max(self.current.len()) {
+            let old = self.last.get(index).copied();
+            let new = self.current.get(index).copied();
+            let element_changed = match (old, new) {
+                (Some(old), Some(new)) => changed(old, new, self.band),
+                _ => true,
+            };
+            if element_changed {
+                self.changes.push(ElementChange { index, old, new });
+            }
+        }
+        if self.changes.is_empty() {
+            return;
+        }
+        // Only changed elements are updated, so that drift within the band accumulates.
+        self.last
+            .resize(self.current.len(), T::Element::from_int(0));
+        for change in &self.changes {
+            if let Some(new) = change.new {
+                self.last[change.index] = new;
+            }
+        }
+        (self.handler)(x, &self.changes);
+    }
+}
+
+struct StringWatch<D, H> {
+    dataref: D,
+    last: String,
+    handler: H,
+}
+
+impl<D: ArrayRead<[u8]>, H> StringWatch<D, H> {
+    fn new(dataref: D, handler: H) -> Self {
+        let last = read_string(&dataref);
+        StringWatch {
+            dataref,
+            last,
+            handler,
+        }
+    }
+}
+
+/// Reads a string dataref, stopping at the first NUL
+fn read_string<D: ArrayRead<[u8]>>(dataref: &D) -> String {
+    let bytes = dataref.as_vec();
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+impl<D, H> Watch for StringWatch<D, H>
+where
+    D: ArrayRead<[u8]> + 'static,
+    H: FnMut(&mut XPAPI, &str, &str) + 'static,
+{
+    fn check(&mut self, x: &mut XPAPI) {
+        let new = read_string(&self.dataref);
+        if new != self.last {
+            let old = std::mem::replace(&mut self.last, new);
+            (self.handler)(x, &old, &self.last);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        ffi::{c_float, c_int, c_void},
+        ptr::NonNull,
+    };
+
+    use super::*;
+    use crate::make_x;
+
+    struct FakeData(Rc<Cell<f32>>);
+
+    impl DataRead<f32> for FakeData {
+        fn get(&self) -> f32 {
+            self.0.get()
+        }
+    }
+
+    struct FakeArray(Rc<RefCell<Vec<i32>>>);
+
+    impl ArrayRead<[i32]> for FakeArray {
+        fn get_range(&self, offset: usize, dest: &mut [i32]) -> usize {
+            let values = self.0.borrow();
+            let values = values.get(offset..).unwrap_or_default();
+            let len = dest.len().min(values.len());
+            dest[..len].copy_from_slice(&values[..len]);
+            len
+        }
+        fn len(&self) -> usize {
+            self.0.borrow().len()
+        }
+    }
+
+    type Callback = unsafe extern "C-unwind" fn(c_float, c_float, c_int, *mut c_void) -> c_float;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_watcher() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let loop_cell: Rc<RefCell<Option<(Callback, *mut c_void)>>> = Rc::new(RefCell::new(None));
+        let loop_cell_1 = loop_cell.clone();
+        let create_ctx = xplane_sys::XPLMCreateFlightLoop_context();
+        create_ctx.expect().once().return_once_st(move |s| {
+            let s = unsafe { *s };
+            assert_eq!(s.phase, FlightLoopPhase::AfterFlightModel);
+            *loop_cell_1.borrow_mut() = Some((s.callbackFunc.unwrap(), s.refcon));
+            expected_ptr
+        });
+        let schedule_ctx = xplane_sys::XPLMScheduleFlightLoop_context();
+        schedule_ctx
+            .expect()
+            .once()
+            .return_once_st(move |_, when, _| assert_eq!(when, -1.0));
+        let destroy_ctx = xplane_sys::XPLMDestroyFlightLoop_context();
+        destroy_ctx.expect().once().return_once_st(|_| ());
+
+        let value = Rc::new(Cell::new(1.0f32));
+        let array = Rc::new(RefCell::new(vec![1, 2, 3]));
+        let scalar_changes = Rc::new(RefCell::new(Vec::new()));
+        let array_changes = Rc::new(RefCell::new(Vec::new()));
+
+        let mut x = make_x();
+        let mut watcher = x
+            .data
+            .new_watcher(FlightLoopPhase::AfterFlightModel, LoopResult::NextLoop);
+        let scalar_changes_1 = scalar_changes.clone();
+        watcher.watch_with_deadband(FakeData(value.clone()), 0.5, move |_, old, new| {
+            scalar_changes_1.borrow_mut().push((old, new));
+        });
+        let array_changes_1 = array_changes.clone();
+        let array_id = watcher.watch_array(FakeArray(array.clone()), move |_, changes| {
+            array_changes_1.borrow_mut().extend_from_slice(changes);
+        });
+        assert_eq!(watcher.len(), 2);
+
+        let (callback, refcon) = loop_cell.borrow_mut().take().unwrap();
+        let run_loop = || unsafe {
+            assert_eq!(callback(0.1, 0.1, 1, refcon), -1.0);
+        };
+
+        run_loop();
+        assert!(scalar_changes.borrow().is_empty());
+        assert!(array_changes.borrow().is_empty());
+
+        // Within the deadband
+        value.set(1.3);
+        run_loop();
+        assert!(scalar_changes.borrow().is_empty());
+        // Drift accumulates against the last reported value
+        value.set(1.75);
+        run_loop();
+        assert_eq!(*scalar_changes.borrow(), [(1.0, 1.75)]);
+
+        array.borrow_mut().clone_from(&vec![1, 5]);
+        run_loop();
+        assert_eq!(
+            *array_changes.borrow(),
+            [
+                ElementChange {
+                    index: 1,
+                    old: Some(2),
+                    new: Some(5)
+                },
+                ElementChange {
+                    index: 2,
+                    old: Some(3),
+                    new: None
+                }
+            ]
+        );
+
+        assert!(watcher.unwatch(array_id));
+        assert!(!watcher.unwatch(array_id));
+        assert_eq!(watcher.len(), 1);
+
+        // Handlers can change the watcher that called them
+        let watcher = Rc::new(RefCell::new(watcher));
+        let watcher_1 = Rc::downgrade(&watcher);
+        let own_id = Rc::new(Cell::new(None));
+        let own_id_1 = own_id.clone();
+        let id = watcher
+            .borrow_mut()
+            .watch(FakeData(value.clone()), move |_, _: f32, _| {
+                let watcher = watcher_1.upgrade().unwrap();
+                let mut watcher = watcher.borrow_mut();
+                assert!(watcher.unwatch(own_id_1.get().unwrap()));
+                watcher.watch_array(FakeArray(Rc::new(RefCell::new(Vec::new()))), |_, _| {});
+            });
+        own_id.set(Some(id));
+        assert_eq!(watcher.borrow().len(), 2);
+        value.set(3.0);
+        run_loop();
+        assert_eq!(scalar_changes.borrow().len(), 2);
+        assert_eq!(watcher.borrow().len(), 2);
+        assert!(!watcher.borrow_mut().unwatch(id));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_watcher_rate() {
+        let expected_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let loop_cell: Rc<RefCell<Option<(Callback, *mut c_void)>>> = Rc::new(RefCell::new(None));
+        let loop_cell_1 = loop_cell.clone();
+        let create_ctx = xplane_sys::XPLMCreateFlightLoop_context();
+        create_ctx.expect().once().return_once_st(move |s| {
+            let s = unsafe { *s };
+            *loop_cell_1.borrow_mut() = Some((s.callbackFunc.unwrap(), s.refcon));
+            expected_ptr
+        });
+        let schedule_ctx = xplane_sys::XPLMScheduleFlightLoop_context();
+        schedule_ctx
+            .expect()
+            .times(3)
+            .returning_st(|_, when, _| assert_eq!(when, -1.0));
+        let destroy_ctx = xplane_sys::XPLMDestroyFlightLoop_context();
+        destroy_ctx.expect().once().return_once_st(|_| ());
+
+        let mut x = make_x();
+        let mut watcher = x.data.new_watcher(
+            FlightLoopPhase::BeforeFlightModel,
+            LoopResult::Seconds(-3.0),
+        );
+        let (callback, refcon) = loop_cell.borrow_mut().take().unwrap();
+        let run_loop = || unsafe { callback(0.1, 0.1, 1, refcon) };
+
+        // Invalid intervals check on every loop
+        assert_eq!(run_loop(), -1.0);
+        assert_eq!(run_loop(), -1.0);
+        watcher.set_rate(LoopResult::Seconds(f32::NAN));
+        assert_eq!(run_loop(), -1.0);
+        assert_eq!(run_loop(), -1.0);
+        watcher.set_rate(LoopResult::Seconds(0.0));
+        assert_eq!(run_loop(), -1.0);
+    }
+}
@@ -0,0 +1,424 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    ffi::{c_char, c_void, CString, NulError},
+    fmt,
+    ops::BitOr,
+    ptr,
+};
+
+use num_enum::TryFromPrimitive;
+use xplane_sys::{
+    XPLMCountHotKeys, XPLMGetHotKeyInfo, XPLMGetNthHotKey, XPLMHotKeyID, XPLMKeyFlags,
+    XPLMPluginID, XPLMRegisterHotKey, XPLMSetHotKeyCombination, XPLMUnregisterHotKey,
+    XPLM_NO_PLUGIN_ID,
+};
+
+use crate::{ffi::StringBuffer, make_x, window::Key, NoSendSync, XPAPI};
+
+/// The size of the buffer that X-Plane writes hot key descriptions into
+const DESCRIPTION_LEN: usize = 512;
+
+/// Struct to access X-Plane's hot key API.
+pub struct HotKeyApi {
+    pub(crate) _phantom: NoSendSync,
+}
+
+impl HotKeyApi {
+    /// Registers a hot key, which calls `handler` when `combination` is pressed.
+    ///
+    /// The hot key is unregistered when the returned [`HotKey`] is dropped.
+    /// # Errors
+    /// Returns an error if the description contains a NUL byte.
+    pub fn register<S: Into<Vec<u8>>>(
+        &mut self,
+        combination: HotKeyCombination,
+        description: S,
+        handler: impl HotKeyHandler,
+    ) -> Result<HotKey, NulError> {
+        HotKey::new(combination, description, handler)
+    }
+
+    /// Returns the number of hot keys registered by all plugins.
+    pub fn count(&mut self) -> usize {
+        let count = unsafe { XPLMCountHotKeys() };
+        usize::try_from(count).unwrap_or(0)
+    }
+
+    /// Returns information about every hot key registered by all plugins.
+    pub fn all(&mut self) -> Vec<HotKeyInfo> {
+        let count = unsafe { XPLMCountHotKeys() };
+        (0..count)
+            .map(|index| unsafe { XPLMGetNthHotKey(index) })
+            .filter(|id| !id.is_null())
+            .map(HotKeyInfo::from_id)
+            .collect()
+    }
+
+    /// Changes the key combination of a hot key registered by any plugin.
+    ///
+    /// This lets users rebind hot keys from a settings screen, for example.
+    pub fn remap(&mut self, hot_key: &HotKeyInfo, combination: HotKeyCombination) {
+        set_combination(hot_key.id, combination);
+    }
+}
+
+/// Modifier keys that are held down as part of a [`HotKeyCombination`]
+///
+/// Modifiers can be combined with `|`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers(u32);
+
+impl Modifiers {
+    /// No modifier keys
+    pub const NONE: Modifiers = Modifiers(0);
+    /// The shift key
+    pub const SHIFT: Modifiers = Modifiers(XPLMKeyFlags::Shift.0);
+    /// The option key on macOS, or the alt key on other platforms
+    pub const OPTION_ALT: Modifiers = Modifiers(XPLMKeyFlags::OptionAlt.0);
+    /// The control key
+    pub const CONTROL: Modifiers = Modifiers(XPLMKeyFlags::Control.0);
+
+    /// Returns true if the shift key is included.
+    #[must_use]
+    pub fn shift(self) -> bool {
+        self.contains(Self::SHIFT)
+    }
+
+    /// Returns true if the option/alt key is included.
+    #[must_use]
+    pub fn option_alt(self) -> bool {
+        self.contains(Self::OPTION_ALT)
+    }
+
+    /// Returns true if the control key is included.
+    #[must_use]
+    pub fn control(self) -> bool {
+        self.contains(Self::CONTROL)
+    }
+
+    /// Returns true if every modifier in `other` is included.
+    #[must_use]
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Gets the modifiers from XPLM key flags, ignoring the up and down flags
+    fn from_flags(flags: XPLMKeyFlags) -> Self {
+        let all = Self::SHIFT | Self::OPTION_ALT | Self::CONTROL;
+        Modifiers(flags.0 & all.0)
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+/// A key and the modifier keys held down with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotKeyCombination {
+    /// The key
+    pub key: Key,
+    /// The modifier keys
+    pub modifiers: Modifiers,
+}
+
+impl HotKeyCombination {
+    /// Creates a combination of `key` and `modifiers`.
+    #[must_use]
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        HotKeyCombination { key, modifiers }
+    }
+
+    /// Returns the virtual key code and flags to pass to X-Plane
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn into_xplm(self) -> (c_char, XPLMKeyFlags) {
+        let virtual_key = u32::from(self.key) as c_char;
+        let flags = XPLMKeyFlags(self.modifiers.0) | XPLMKeyFlags::Down;
+        (virtual_key, flags)
+    }
+}
+
+impl From<Key> for HotKeyCombination {
+    fn from(key: Key) -> Self {
+        HotKeyCombination::new(key, Modifiers::NONE)
+    }
+}
+
+/// Trait for things that can respond when a hot key is pressed
+pub trait HotKeyHandler: 'static {
+    /// Called when the hot key is pressed
+    fn hot_key_pressed(&mut self, x: &mut XPAPI);
+}
+
+impl<F> HotKeyHandler for F
+where
+    F: FnMut(&mut XPAPI) + 'static,
+{
+    fn hot_key_pressed(&mut self, x: &mut XPAPI) {
+        self(x);
+    }
+}
+
+/// A hot key registered by this plugin
+///
+/// The hot key is unregistered when this is dropped.
+pub struct HotKey {
+    /// The heap-allocated data
+    data: *mut HotKeyData,
+}
+
+/// Data for a hot key, used as a refcon
+struct HotKeyData {
+    /// The hot key ID, or null while it is being registered
+    id: XPLMHotKeyID,
+    /// The handler
+    handler: Box<dyn HotKeyHandler>,
+}
+
+impl HotKey {
+    fn new<S: Into<Vec<u8>>>(
+        combination: HotKeyCombination,
+        description: S,
+        handler: impl HotKeyHandler,
+    ) -> Result<Self, NulError> {
+        let description_c = CString::new(description)?;
+        let data = Box::into_raw(Box::new(HotKeyData {
+            id: ptr::null_mut(),
+            handler: Box::new(handler),
+        }));
+        let (virtual_key, flags) = combination.into_xplm();
+        unsafe {
+            (*data).id = XPLMRegisterHotKey(
+                virtual_key,
+                flags,
+                description_c.as_ptr(),
+                Some(hot_key_callback),
+                data.cast::<c_void>(),
+            );
+        }
+        Ok(HotKey { data })
+    }
+
+    /// Returns information about this hot key, including its current key combination.
+    #[must_use]
+    pub fn info(&self) -> HotKeyInfo {
+        HotKeyInfo::from_id(unsafe { (*self.data).id })
+    }
+
+    /// Changes the key combination of this hot key.
+    pub fn set_combination(&mut self, combination: HotKeyCombination) {
+        set_combination(unsafe { (*self.data).id }, combination);
+    }
+}
+
+impl Drop for HotKey {
+    fn drop(&mut self) {
+        unsafe {
+            XPLMUnregisterHotKey((*self.data).id);
+            let _ = Box::from_raw(self.data);
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl fmt::Debug for HotKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotKey")
+            .field("id", &"[hot key handle]")
+            .field("handler", &"[handler]")
+            .finish()
+    }
+}
+
+/// Information about a hot key registered by any plugin
+#[derive(Debug, Clone)]
+pub struct HotKeyInfo {
+    id: XPLMHotKeyID,
+    virtual_key: c_char,
+    modifiers: Modifiers,
+    description: String,
+    plugin: XPLMPluginID,
+}
+
+impl HotKeyInfo {
+    /// Queries X-Plane for information about a hot key.
+    fn from_id(id: XPLMHotKeyID) -> Self {
+        let mut virtual_key: c_char = 0;
+        let mut flags = XPLMKeyFlags(0);
+        let mut description = StringBuffer::new(DESCRIPTION_LEN);
+        let mut plugin = XPLM_NO_PLUGIN_ID;
+        unsafe {
+            XPLMGetHotKeyInfo(
+                id,
+                &mut virtual_key,
+                &mut flags,
+                description.as_mut_ptr(),
+                &mut plugin,
+            );
+        }
+        HotKeyInfo {
+            id,
+            virtual_key,
+            modifiers: Modifiers::from_flags(flags),
+            description: description.as_str().unwrap_or_default().to_owned(),
+            plugin,
+        }
+    }
+
+    /// Returns the key, or `None` if X-Plane uses a virtual key code that [`Key`] does not
+    /// include.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn key(&self) -> Option<Key> {
+        Key::try_from_primitive(u32::from(self.virtual_key as u8)).ok()
+    }
+
+    /// Returns the modifier keys.
+    #[must_use]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Returns the key combination, or `None` if the key is not one that [`Key`] includes.
+    #[must_use]
+    pub fn combination(&self) -> Option<HotKeyCombination> {
+        self.key()
+            .map(|key| HotKeyCombination::new(key, self.modifiers))
+    }
+
+    /// Returns the description of the hot key.
+    #[must_use]
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the ID of the plugin that registered the hot key.
+    #[must_use]
+    pub fn plugin(&self) -> XPLMPluginID {
+        self.plugin
+    }
+}
+
+fn set_combination(id: XPLMHotKeyID, combination: HotKeyCombination) {
+    let (virtual_key, flags) = combination.into_xplm();
+    unsafe {
+        XPLMSetHotKeyCombination(id, virtual_key, flags);
+    }
+}
+
+/// Hot key callback
+unsafe extern "C-unwind" fn hot_key_callback(refcon: *mut c_void) {
+    let data = refcon.cast::<HotKeyData>();
+    let mut x = make_x();
+    unsafe {
+        (*data).handler.hot_key_pressed(&mut x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, ptr::NonNull, rc::Rc};
+
+    use xplane_sys::{XPLM_VK_F1, XPLM_VK_F2};
+
+    use super::*;
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn test_hot_keys() {
+        let hot_key_ptr = NonNull::<c_void>::dangling().as_ptr();
+        let other_ptr = hot_key_ptr.cast::<u8>().wrapping_add(1).cast::<c_void>();
+        let refcon_cell = Rc::new(RefCell::new(ptr::null_mut()));
+        let refcon_cell_1 = refcon_cell.clone();
+        let register_ctx = xplane_sys::XPLMRegisterHotKey_context();
+        register_ctx.expect().once().return_once_st(
+            move |virtual_key, flags, description, callback, refcon| {
+                assert_eq!(virtual_key, XPLM_VK_F1 as c_char);
+                assert_eq!(
+                    flags.0,
+                    (XPLMKeyFlags::Control | XPLMKeyFlags::Shift | XPLMKeyFlags::Down).0
+                );
+                let description = unsafe { std::ffi::CStr::from_ptr(description) };
+                assert_eq!(description.to_str().unwrap(), "Toggle the panel");
+                assert!(callback.is_some());
+                *refcon_cell_1.borrow_mut() = refcon;
+                hot_key_ptr
+            },
+        );
+        let unregister_ctx = xplane_sys::XPLMUnregisterHotKey_context();
+        unregister_ctx
+            .expect()
+            .once()
+            .return_once_st(move |id| assert_eq!(id, hot_key_ptr));
+        let count_ctx = xplane_sys::XPLMCountHotKeys_context();
+        count_ctx.expect().returning_st(|| 2);
+        let nth_ctx = xplane_sys::XPLMGetNthHotKey_context();
+        nth_ctx
+            .expect()
+            .returning_st(move |index| if index == 0 { other_ptr } else { hot_key_ptr });
+        let info_ctx = xplane_sys::XPLMGetHotKeyInfo_context();
+        info_ctx
+            .expect()
+            .returning_st(move |id, virtual_key, flags, description, plugin| {
+                let (key, text, owner): (u32, &[u8], _) = if id == hot_key_ptr {
+                    (XPLM_VK_F1, b"Toggle the panel\0", 3)
+                } else {
+                    (0xFF, b"Something else\0", 4)
+                };
+                unsafe {
+                    *virtual_key = key as c_char;
+                    *flags = XPLMKeyFlags::Shift | XPLMKeyFlags::Down;
+                    ptr::copy_nonoverlapping(text.as_ptr().cast(), description, text.len());
+                    *plugin = owner;
+                }
+            });
+        let set_ctx = xplane_sys::XPLMSetHotKeyCombination_context();
+        set_ctx
+            .expect()
+            .times(2)
+            .returning_st(|_, virtual_key, flags| {
+                assert_eq!(virtual_key, XPLM_VK_F2 as c_char);
+                assert_eq!(flags.0, (XPLMKeyFlags::OptionAlt | XPLMKeyFlags::Down).0);
+            });
+
+        let presses = Rc::new(RefCell::new(0));
+        let presses_1 = presses.clone();
+        let mut x = make_x();
+        let mut hot_key = x
+            .hot_keys
+            .register(
+                HotKeyCombination::new(Key::F1, Modifiers::CONTROL | Modifiers::SHIFT),
+                "Toggle the panel",
+                move |_: &mut XPAPI| *presses_1.borrow_mut() += 1,
+            )
+            .unwrap();
+        unsafe {
+            hot_key_callback(*refcon_cell.borrow());
+        }
+        assert_eq!(*presses.borrow(), 1);
+
+        assert_eq!(x.hot_keys.count(), 2);
+        let all = x.hot_keys.all();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].key(), None);
+        assert_eq!(all[0].description(), "Something else");
+        assert_eq!(all[0].plugin(), 4);
+        let info = hot_key.info();
+        assert_eq!(
+            info.combination(),
+            Some(HotKeyCombination::new(Key::F1, Modifiers::SHIFT))
+        );
+        assert!(info.modifiers().shift());
+        assert!(!info.modifiers().control());
+
+        let remapped = HotKeyCombination::new(Key::F2, Modifiers::OPTION_ALT);
+        x.hot_keys.remap(&all[0], remapped.clone());
+        hot_key.set_combination(remapped);
+    }
+}
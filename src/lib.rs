@@ -17,6 +17,7 @@ use crate::command::CommandApi;
 use crate::data::DataApi;
 use crate::feature::FeatureApi;
 use crate::flight_loop::{FlightLoop, FlightLoopCallback, FlightLoopPhase};
+use crate::hotkey::HotKeyApi;
 use crate::menu::MenuApi;
 use crate::navigation::{Fms, NavApi};
 use crate::paths::PathApi;
@@ -39,6 +40,10 @@ use xplane_sys::{
     XPLMHostApplicationID, XPLMLanguageCode, XPLMSpeakString,
 };
 
+// Lets the derive macros' generated code refer to this crate as `::xplane` in tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as xplane;
+
 /// FFI utilities
 mod ffi;
 /// Plugin macro
@@ -64,6 +69,8 @@ pub mod feature;
 /// Flight loop callbacks
 pub mod flight_loop;
 pub mod geometry;
+/// Hot keys
+pub mod hotkey;
 /// User interface menus
 pub mod menu;
 /// Plugin messages
@@ -111,6 +118,7 @@ pub struct XPAPI {
     pub command: CommandApi,
     pub data: DataApi,
     pub features: FeatureApi,
+    pub hot_keys: HotKeyApi,
     pub menu: MenuApi,
     pub nav: NavApi,
     pub paths: PathApi,
@@ -240,6 +248,9 @@ fn make_x() -> XPAPI {
         features: FeatureApi {
             _phantom: PhantomData,
         },
+        hot_keys: HotKeyApi {
+            _phantom: PhantomData,
+        },
         menu: MenuApi {
             _phantom: PhantomData,
         },
@@ -68,6 +68,8 @@ where
     let plugin = unsafe { Box::from_raw(data.plugin) };
     data.plugin = ptr::null_mut();
     drop(plugin);
+    crate::command::release_all_holds();
+    crate::data::editor::shutdown();
 }
 
 /// Implements the `XPluginEnable` callback
@@ -94,6 +96,8 @@ where
     unsafe {
         (*data.plugin).disable(&mut x);
     }
+    crate::command::release_all_holds();
+    crate::data::overrides::release_all();
 }
 
 /// Implements the `XPluginReceiveMessage` callback
@@ -106,7 +110,11 @@ pub unsafe fn xplugin_receive_message<P>(
     P: Plugin,
 {
     let mut x = make_x();
+    let message = message.into();
+    crate::data::editor::handle_message(message, param);
+    crate::data::lazy::handle_message(message, param);
+    crate::data::proxy::handle_message(message, param);
     unsafe {
-        (*data.plugin).receive_message(&mut x, from, message.into(), param);
+        (*data.plugin).receive_message(&mut x, from, message, param);
     }
 }
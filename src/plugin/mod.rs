@@ -48,6 +48,10 @@ pub trait Plugin: Sized {
     /// Called when the plugin is disabled
     ///
     /// The default implementation does nothing.;
+    ///
+    /// After this returns, every [`OverrideGuard`](crate::data::overrides::OverrideGuard)
+    /// and [`OwnedCommandHold`](crate::command::OwnedCommandHold) that is still held is
+    /// released.
     fn disable(&mut self, xpapi: &mut XPAPI);
 
     /// Returns information on this plugin
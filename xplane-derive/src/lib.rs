@@ -0,0 +1,154 @@
+// SPDX-FileCopyrightText: 2024 Julia DeMille <me@jdemille.com>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Derive macros for the [`xplane`](https://docs.rs/xplane) crate.
+//!
+//! These are re-exported by `xplane` when its `derive` feature is enabled, and should be
+//! used from there.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Data, DeriveInput, Error, Fields, Ident, LitStr, Token,
+};
+
+/// Derives `xplane::data::DataRefBundle` for a struct whose fields are all datarefs.
+///
+/// Every field needs a `#[dataref("name")]` attribute with the name of the dataref to find.
+/// Fields that should be found as writable also need `writable`, either in the same attribute
+/// (`#[dataref("name", writable)]`) or in a separate `#[dataref(writable)]` attribute.
+///
+/// The generated `find_all` function tries to find every field, and returns the failures for
+/// all the datarefs that could not be found.
+#[proc_macro_derive(DataRefs, attributes(dataref))]
+pub fn derive_data_refs(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// One argument inside a `#[dataref(...)]` attribute
+enum DataRefArg {
+    /// The dataref name
+    Name(LitStr),
+    /// The `writable` flag
+    Writable,
+}
+
+impl Parse for DataRefArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return input.parse().map(DataRefArg::Name);
+        }
+        let ident: Ident = input.parse()?;
+        if ident == "writable" {
+            Ok(DataRefArg::Writable)
+        } else {
+            Err(Error::new(
+                ident.span(),
+                "expected a dataref name or `writable`",
+            ))
+        }
+    }
+}
+
+/// The settings for one field, from its `#[dataref(...)]` attributes
+struct FieldSettings {
+    name: LitStr,
+    writable: bool,
+}
+
+impl FieldSettings {
+    fn from_field(field: &syn::Field) -> syn::Result<Self> {
+        let mut name = None;
+        let mut writable = false;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("dataref")) {
+            let args =
+                attr.parse_args_with(Punctuated::<DataRefArg, Token![,]>::parse_terminated)?;
+            for arg in args {
+                match arg {
+                    DataRefArg::Name(lit) => {
+                        if name.is_some() {
+                            return Err(Error::new(lit.span(), "duplicate dataref name"));
+                        }
+                        name = Some(lit);
+                    }
+                    DataRefArg::Writable => writable = true,
+                }
+            }
+        }
+        let name = name.ok_or_else(|| {
+            Error::new(
+                field.span(),
+                "missing dataref name; add #[dataref(\"sim/...\")] to this field",
+            )
+        })?;
+        Ok(FieldSettings { name, writable })
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            Span::call_site(),
+            "DataRefs can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            data.fields.span(),
+            "DataRefs can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut idents = Vec::new();
+    let mut finds = Vec::new();
+    for field in &fields.named {
+        let settings = FieldSettings::from_field(field)?;
+        // UNWRAP: These are named fields.
+        let ident = field.ident.clone().unwrap();
+        let name = settings.name;
+        let find = if settings.writable {
+            quote! { __errors.__find_writable(data, #name) }
+        } else {
+            quote! { __errors.__find(data, #name) }
+        };
+        finds.push(quote! { let #ident = #find; });
+        idents.push(ident);
+    }
+
+    let ty = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let body = if idents.is_empty() {
+        quote! {
+            let _ = data;
+            ::core::result::Result::Ok(Self {})
+        }
+    } else {
+        quote! {
+            let mut __errors = ::xplane::data::FindAllError::__new();
+            #(#finds)*
+            let (#(::core::option::Option::Some(#idents),)*) = (#(#idents,)*) else {
+                return ::core::result::Result::Err(__errors);
+            };
+            ::core::result::Result::Ok(Self { #(#idents),* })
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::xplane::data::DataRefBundle for #ty #ty_generics #where_clause {
+            fn find_all(
+                data: &mut ::xplane::data::DataApi,
+            ) -> ::core::result::Result<Self, ::xplane::data::FindAllError> {
+                #body
+            }
+        }
+    })
+}